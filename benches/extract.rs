@@ -0,0 +1,70 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use mpkg_unpack::{extract_file, unpack_with_options, LogLevel, UnpackOptions};
+use tempfile::TempDir;
+
+/// 写入一个包含 `count` 个大小均为 `size` 字节的文件的MPKG文件
+fn write_archive(path: &Path, count: usize, size: usize) {
+    let version = b"PKGV0001";
+    let mut bytes = Vec::new();
+    bytes.extend_from_slice(&(version.len() as u32).to_le_bytes());
+    bytes.extend_from_slice(version);
+    bytes.extend_from_slice(&(count as u32).to_le_bytes());
+    for i in 0..count {
+        let name = format!("files/{}.bin", i);
+        bytes.extend_from_slice(&(name.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(name.as_bytes());
+        bytes.extend_from_slice(&0u32.to_le_bytes());
+        bytes.extend_from_slice(&(size as u32).to_le_bytes());
+    }
+    for i in 0..count {
+        bytes.resize(bytes.len() + size, (i % 251) as u8);
+    }
+    fs::write(path, bytes).unwrap();
+}
+
+/// 基准测试使用的临时文件夹，离开作用域时连同其中的测试数据一起删除
+fn bench_dir(name: &str) -> TempDir {
+    tempfile::Builder::new()
+        .prefix(&format!("mpkg_unpack_bench_{}_", name))
+        .tempdir()
+        .unwrap()
+}
+
+fn extract(c: &mut Criterion) {
+    let options = UnpackOptions::builder()
+        .log_level(LogLevel::Quiet)
+        .build()
+        .unwrap();
+
+    let small_dir = bench_dir("small");
+    let small_archive = small_dir.path().join("small.mpkg");
+    let small_out = small_dir.path().join("out");
+    write_archive(&small_archive, 10_000, 64);
+    c.bench_function("10k small files", |b| {
+        b.iter(|| unpack_with_options(&small_archive, &small_out, &options).unwrap())
+    });
+    // 按名称提取位于末尾的单个文件，之前的文件都被跳过
+    c.bench_function("one of 10k files", |b| {
+        b.iter(|| assert!(extract_file(&small_archive, "files/9999.bin", &mut io::sink()).unwrap()))
+    });
+    drop(small_dir);
+
+    let large_dir = bench_dir("large");
+    let large_archive = large_dir.path().join("large.mpkg");
+    let large_out = large_dir.path().join("out");
+    write_archive(&large_archive, 1, 256 * 1024 * 1024);
+    c.bench_function("one 256MB file", |b| {
+        b.iter(|| unpack_with_options(&large_archive, &large_out, &options).unwrap())
+    });
+}
+
+criterion_group! {
+    name = benches;
+    config = Criterion::default().sample_size(10);
+    targets = extract
+}
+criterion_main!(benches);
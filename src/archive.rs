@@ -0,0 +1,306 @@
+use std::cell::RefCell;
+use std::fs::{self, File};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+use std::rc::Rc;
+use std::slice;
+
+use crate::buffered::SeekBufReader;
+use crate::parse::ArchiveTable;
+use crate::targets::plan_targets;
+use crate::{
+    copy_entry_data, open_archive, unpack_entries, MpkgEntry, MpkgError, PreparedArchive,
+    UnpackOptions, UnpackReport,
+};
+
+/// 各个 `EntryReader` 共用的文件句柄及其当前位置
+struct SharedReader {
+    reader: SeekBufReader<File>,
+    position: u64,
+}
+
+/// 已打开的MPKG文件，可逐个读取其中文件的数据
+///
+/// 文件表只在打开时解析一次，之后的查询与提取都复用它并按需定位，适合交互式工具反复访问同一个包。
+pub struct MpkgArchive {
+    table: ArchiveTable,
+    shared: Rc<RefCell<SharedReader>>,
+}
+
+impl MpkgArchive {
+    /// 打开MPKG文件并读取头部与文件表
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<MpkgArchive, MpkgError> {
+        Self::open_with_options(path, &UnpackOptions::default())
+    }
+
+    /// 按给定选项打开MPKG文件，只使用其中与读取文件表有关的选项
+    pub fn open_with_options<P: AsRef<Path>>(
+        path: P,
+        options: &UnpackOptions,
+    ) -> Result<MpkgArchive, MpkgError> {
+        let (mut reader, table) = open_archive(path.as_ref(), options)?;
+        // 文件表位于末尾时读取后停在文件表末尾，而不是第一个文件数据处
+        let position = reader.stream_position()?;
+        Ok(MpkgArchive {
+            table,
+            shared: Rc::new(RefCell::new(SharedReader { reader, position })),
+        })
+    }
+
+    /// 头部的格式版本字符串
+    pub fn version(&self) -> &str {
+        &self.table.version
+    }
+
+    /// 文件表中的所有文件
+    pub fn entry_list(&self) -> &[MpkgEntry] {
+        &self.table.entries
+    }
+
+    /// 检查文件长度是否足以容纳文件表所描述的全部数据
+    pub(crate) fn check_complete(&self) -> Result<(), MpkgError> {
+        self.table.check_complete()
+    }
+
+    /// 查找名为 `name` 的文件，文件名需与包内存储的名称完全一致
+    fn find(&self, name: &str) -> Result<&MpkgEntry, MpkgError> {
+        self.table
+            .entries
+            .iter()
+            .find(|entry| entry.name == name)
+            .ok_or_else(|| MpkgError::EntryNotFound(name.to_string()))
+    }
+
+    /// 返回名为 `name` 的文件的读取器，文件名需与包内存储的名称完全一致
+    ///
+    /// 读取器可以在该文件的数据范围内定位，位置从该文件数据的起始处算起。
+    pub fn reader_for(&self, name: &str) -> Result<EntryReader, MpkgError> {
+        Ok(self.reader(self.find(name)?))
+    }
+
+    /// 将名为 `name` 的文件写入 `out`，返回写入的字节数
+    ///
+    /// 与 `extract_file` 相同，按默认选项原样复制数据，不解压被标记为压缩的文件。
+    pub fn extract<W: Write>(&self, name: &str, out: &mut W) -> Result<u64, MpkgError> {
+        let entry = self.find(name)?;
+        let options = UnpackOptions::default();
+        let mut shared = self.shared.borrow_mut();
+        shared.reader.seek(SeekFrom::Start(entry.offset))?;
+        let result = copy_entry_data(&mut shared.reader, out, entry, &options, |_| {});
+        shared.position = shared.reader.stream_position()?;
+        result
+    }
+
+    /// 按默认选项将所有文件直接解包到 `output_dir`，不再创建以包名命名的子文件夹
+    ///
+    /// 写入前先检查文件是否完整、文件名是否安全，不打印任何进度。
+    pub fn extract_all<P: AsRef<Path>>(&self, output_dir: P) -> Result<UnpackReport, MpkgError> {
+        let output_dir = output_dir.as_ref();
+        let options = UnpackOptions::default();
+        self.check_complete()?;
+        let archive = PreparedArchive {
+            version: self.table.version.clone(),
+            entries: self.table.entries.clone(),
+            targets: plan_targets(&self.table.entries, output_dir, &options)?,
+        };
+        fs::create_dir_all(output_dir)?;
+
+        let mut report = UnpackReport {
+            version: archive.version.clone(),
+            output_dir: output_dir.to_path_buf(),
+            ..UnpackReport::default()
+        };
+        let mut shared = self.shared.borrow_mut();
+        let result = unpack_entries(
+            &mut shared.reader,
+            output_dir,
+            &archive,
+            &options,
+            &mut report,
+            &mut |_| {},
+        );
+        shared.position = shared.reader.stream_position()?;
+        result.map(|_| report)
+    }
+
+    /// 创建只能读取 `entry` 数据的读取器
+    pub(crate) fn reader(&self, entry: &MpkgEntry) -> EntryReader {
+        EntryReader {
+            shared: Rc::clone(&self.shared),
+            start: entry.offset,
+            position: entry.offset,
+            end: entry.offset.saturating_add(entry.size),
+        }
+    }
+
+    /// 按文件表顺序遍历文件，同时给出只能读取该文件数据的读取器
+    ///
+    /// 读取器之间共用同一个文件句柄，读取时按需定位，
+    /// 因此未读完的数据会被直接跳过，也可以随时停止遍历。
+    /// 数据块按原样返回，不会解压。
+    pub fn entries(&self) -> Entries<'_> {
+        Entries {
+            archive: self,
+            entries: self.table.entries.iter(),
+        }
+    }
+}
+
+/// `MpkgArchive::entries` 返回的迭代器
+pub struct Entries<'a> {
+    archive: &'a MpkgArchive,
+    entries: slice::Iter<'a, MpkgEntry>,
+}
+
+impl Iterator for Entries<'_> {
+    type Item = (MpkgEntry, EntryReader);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let entry = self.entries.next()?;
+        Some((entry.clone(), self.archive.reader(entry)))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.entries.size_hint()
+    }
+}
+
+impl ExactSizeIterator for Entries<'_> {}
+
+/// 单个文件数据的读取器，最多读取到该文件数据的末尾
+///
+/// 定位时的位置从该文件数据的起始处算起，超出数据范围的位置被限制在起始处或末尾。
+pub struct EntryReader {
+    shared: Rc<RefCell<SharedReader>>,
+    start: u64,
+    position: u64,
+    end: u64,
+}
+
+impl Read for EntryReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let remaining = self.end - self.position;
+        if remaining == 0 || buf.is_empty() {
+            return Ok(0);
+        }
+        let to_read = std::cmp::min(buf.len() as u64, remaining) as usize;
+
+        let mut shared = self.shared.borrow_mut();
+        if shared.position != self.position {
+            shared.reader.seek(SeekFrom::Start(self.position))?;
+            shared.position = self.position;
+        }
+        let bytes_read = shared.reader.read(&mut buf[..to_read])?;
+        if bytes_read == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "文件数据提前结束",
+            ));
+        }
+        shared.position += bytes_read as u64;
+        self.position += bytes_read as u64;
+        Ok(bytes_read)
+    }
+}
+
+impl Seek for EntryReader {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let size = self.end - self.start;
+        let (base, delta) = match pos {
+            SeekFrom::Start(offset) => (0, i128::from(offset)),
+            SeekFrom::End(delta) => (size, i128::from(delta)),
+            SeekFrom::Current(delta) => (self.position - self.start, i128::from(delta)),
+        };
+        // 只移动自身的位置，共用的文件句柄在下次读取时再定位
+        let target = (i128::from(base) + delta).clamp(0, i128::from(size)) as u64;
+        self.position = self.start + target;
+        Ok(target)
+    }
+}
+
+/// 在任意可定位的流上只读取单个文件数据的读取器，读到该文件数据末尾时返回EOF
+///
+/// 创建时定位到文件数据的起始处；释放时把流定位到文件数据末尾，
+/// 即使数据没有读完，之后也可以直接读取下一个文件。
+pub struct BoundedReader<'a, R: Read + Seek> {
+    inner: &'a mut R,
+    position: u64,
+    end: u64,
+}
+
+impl<'a, R: Read + Seek> BoundedReader<'a, R> {
+    /// 定位到 `entry` 的数据起始处并创建读取器
+    pub fn new(inner: &'a mut R, entry: &MpkgEntry) -> io::Result<BoundedReader<'a, R>> {
+        inner.seek(SeekFrom::Start(entry.offset))?;
+        Ok(BoundedReader {
+            inner,
+            position: entry.offset,
+            end: entry.offset.saturating_add(entry.size),
+        })
+    }
+
+    /// 尚未读取的字节数
+    pub fn remaining(&self) -> u64 {
+        self.end - self.position
+    }
+}
+
+impl<R: Read + Seek> Read for BoundedReader<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let remaining = self.remaining();
+        if remaining == 0 || buf.is_empty() {
+            return Ok(0);
+        }
+        let to_read = std::cmp::min(buf.len() as u64, remaining) as usize;
+        let bytes_read = self.inner.read(&mut buf[..to_read])?;
+        if bytes_read == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "文件数据提前结束",
+            ));
+        }
+        self.position += bytes_read as u64;
+        Ok(bytes_read)
+    }
+}
+
+impl<R: Read + Seek> Drop for BoundedReader<'_, R> {
+    fn drop(&mut self) {
+        if self.position != self.end {
+            let _ = self.inner.seek(SeekFrom::Start(self.end));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::ArchiveBuilder;
+
+    #[test]
+    fn entry_reader_seeks_within_entry() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("a.mpkg");
+        let bytes = ArchiveBuilder::new("PKGV0001")
+            .file("a.txt", b"abcdef")
+            .file("b.txt", b"ghi")
+            .build();
+        fs::write(&path, bytes).unwrap();
+        let archive = MpkgArchive::open(&path).unwrap();
+        let mut reader = archive.reader_for("a.txt").unwrap();
+
+        let mut data = String::new();
+        assert_eq!(reader.seek(SeekFrom::Start(2)).unwrap(), 2);
+        reader.read_to_string(&mut data).unwrap();
+        assert_eq!(data, "cdef");
+
+        // 超出数据范围的位置被限制在起始处或末尾，不会读到相邻文件的数据
+        assert_eq!(reader.seek(SeekFrom::Current(-100)).unwrap(), 0);
+        assert_eq!(reader.seek(SeekFrom::End(5)).unwrap(), 6);
+        assert_eq!(reader.read(&mut [0; 4]).unwrap(), 0);
+        data.clear();
+        assert_eq!(reader.seek(SeekFrom::End(-1)).unwrap(), 5);
+        reader.read_to_string(&mut data).unwrap();
+        assert_eq!(data, "f");
+    }
+}
@@ -0,0 +1,187 @@
+use std::io::{self, Cursor, Read, Seek, SeekFrom};
+use std::path::Path;
+use std::time::{Duration, UNIX_EPOCH};
+
+use tokio::fs::{self, File};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncSeek, AsyncSeekExt, AsyncWriteExt, BufWriter};
+
+use crate::format::{Layout, FOOTER_LEN};
+use crate::parse::{parse_archive_table, read_header, ArchiveTable};
+use crate::targets::{check_total_size, plan_targets};
+use crate::{
+    apply_default_mode, record_skipped, should_write, MpkgError, UnpackOptions, UnpackReport,
+};
+
+/// 文件表不完整时每次追加读取的字节数
+const TABLE_CHUNK: u64 = 64 * 1024;
+
+/// 只保存了文件开头与末尾两段数据的读取器，供同步的解析逻辑读取文件表
+///
+/// 位置按整个文件计算，读到两段之外的位置时视为EOF。
+struct PartialFile {
+    head: Vec<u8>,
+    tail_start: u64,
+    tail: Vec<u8>,
+    archive_len: u64,
+    position: u64,
+}
+
+impl Read for PartialFile {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let (segment, start) = if self.position < self.head.len() as u64 {
+            (&self.head, 0)
+        } else if self.position >= self.tail_start {
+            (&self.tail, self.tail_start)
+        } else {
+            return Ok(0);
+        };
+        let from = usize::try_from(self.position - start).unwrap_or(usize::MAX);
+        let bytes_read = io::Read::read(&mut segment.get(from..).unwrap_or_default(), buf)?;
+        self.position += bytes_read as u64;
+        Ok(bytes_read)
+    }
+}
+
+impl Seek for PartialFile {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let position = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::End(offset) => self.archive_len.checked_add_signed(offset),
+            SeekFrom::Current(offset) => self.position.checked_add_signed(offset),
+        };
+        self.position = position
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "定位到了文件开头之前"))?;
+        Ok(self.position)
+    }
+}
+
+/// 从流的开头异步读取头部与文件表
+///
+/// 先把头部与文件表所在的部分读入内存，再交给同步的解析逻辑：
+/// 文件表在前时从开头读取，超出已读取的部分时加倍读取，直到解析成功或读到文件末尾；
+/// 文件表在后时按文件末尾记录的偏移读取文件表。
+async fn read_table<R>(reader: &mut R, options: &UnpackOptions) -> Result<ArchiveTable, MpkgError>
+where
+    R: AsyncRead + AsyncSeek + Unpin,
+{
+    let archive_len = reader.seek(SeekFrom::End(0)).await?;
+    reader.seek(SeekFrom::Start(0)).await?;
+
+    // 头部必须完整读入，否则解析时会被误判为头部长度超出文件大小
+    let mut head = Vec::new();
+    (&mut *reader).take(4).read_to_end(&mut head).await?;
+    let header_length = match head[..] {
+        [a, b, c, d] => u32::from_le_bytes([a, b, c, d]).min(options.max_header_length as u32),
+        _ => 0,
+    };
+    let mut bytes_read = (&mut *reader)
+        .take(header_length as u64 + TABLE_CHUNK)
+        .read_to_end(&mut head)
+        .await?;
+
+    // 头部无效时交给下面的解析报告错误
+    let trailing_index = read_header(&mut Cursor::new(&head[..]), Some(archive_len), options)
+        .is_ok_and(|version| Layout::for_version(&version).trailing_index);
+    let mut file = PartialFile {
+        head,
+        tail_start: archive_len,
+        tail: Vec::new(),
+        archive_len,
+        position: 0,
+    };
+    if trailing_index && archive_len >= FOOTER_LEN {
+        let mut footer = [0u8; FOOTER_LEN as usize];
+        reader.seek(SeekFrom::End(-(FOOTER_LEN as i64))).await?;
+        reader.read_exact(&mut footer).await?;
+        file.tail_start = u64::from_le_bytes(footer).min(archive_len - FOOTER_LEN);
+        reader.seek(SeekFrom::Start(file.tail_start)).await?;
+        reader.read_to_end(&mut file.tail).await?;
+        bytes_read = 0;
+    }
+
+    loop {
+        file.position = 0;
+        match parse_archive_table(&mut file, archive_len, options) {
+            Err(MpkgError::TruncatedEntryTable { .. }) if bytes_read > 0 => {
+                let chunk = file.head.len() as u64;
+                bytes_read = (&mut *reader)
+                    .take(chunk)
+                    .read_to_end(&mut file.head)
+                    .await?;
+            }
+            result => return result,
+        }
+    }
+}
+
+/// 使用 `tokio` 从异步流中解包MPKG文件，输出到 `output_dir` 下名为 `name_hint` 的文件夹
+///
+/// 与 `unpack_mpkg_from_reader` 对应，文件表的解析与同步版本共用。
+/// 支持 `filter`、`exclude`、`flatten`、`rename`、`overwrite`、`skip_empty`、
+/// `preserve_timestamps`、`default_mode` 与 `max_total_size` 等选项；数据块按原样写出，不解压也不校验CRC32，不打印任何进度。
+pub async fn unpack_mpkg_async<R, P>(
+    mut reader: R,
+    output_dir: P,
+    name_hint: &str,
+    options: &UnpackOptions,
+) -> Result<UnpackReport, MpkgError>
+where
+    R: AsyncRead + AsyncSeek + Unpin,
+    P: AsRef<Path>,
+{
+    options.validate()?;
+    let unpacked_folder = output_dir.as_ref().join(name_hint);
+
+    // 读取头部信息并构建文件列表，全部有效后再创建输出文件夹
+    let table = read_table(&mut reader, options).await?;
+    table.check_complete()?;
+    let targets = plan_targets(&table.entries, &unpacked_folder, options)?;
+    check_total_size(&table.entries, &targets, options)?;
+    fs::create_dir_all(&unpacked_folder).await?;
+
+    let mut report = UnpackReport {
+        version: table.version.clone(),
+        output_dir: unpacked_folder.clone(),
+        ..UnpackReport::default()
+    };
+    for (entry, target) in table.entries.iter().zip(&targets) {
+        let target = match target {
+            Some(target) if should_write(target, options.overwrite)? => target,
+            _ => {
+                record_skipped(&mut report, entry, options);
+                continue;
+            }
+        };
+
+        if let Some(parent_dir) = target.parent() {
+            fs::create_dir_all(parent_dir).await?;
+        }
+        reader.seek(SeekFrom::Start(entry.offset)).await?;
+
+        // 复制数据
+        let capacity = options
+            .buffer_size
+            .min(usize::try_from(entry.size).unwrap_or(usize::MAX));
+        let mut output_stream = BufWriter::with_capacity(capacity, File::create(target).await?);
+        let copied =
+            tokio::io::copy(&mut (&mut reader).take(entry.size), &mut output_stream).await?;
+        if copied != entry.size {
+            return Err(MpkgError::DataTruncated {
+                expected: entry.size,
+                got: copied,
+                offset: entry.offset + copied,
+            });
+        }
+        output_stream.flush().await?;
+
+        // 包内记录了修改时间时应用到输出文件，再设置权限
+        let output_file = output_stream.into_inner().into_std().await;
+        if let (true, Some(modified)) = (options.preserve_timestamps, entry.modified) {
+            output_file.set_modified(UNIX_EPOCH + Duration::from_secs(modified))?;
+        }
+        apply_default_mode(&output_file, options.default_mode)?;
+        report.files_extracted += 1;
+        report.bytes_written += copied;
+    }
+    Ok(report)
+}
@@ -0,0 +1,75 @@
+use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom};
+
+/// 定位时尽量保留缓冲区的 `BufReader`
+///
+/// `BufReader::seek` 每次都会丢弃缓冲区，筛选或按名称提取时频繁跳过文件，
+/// 同一块数据会被反复读取。这里自行记录当前位置，把绝对定位换算为 `seek_relative`：
+/// 目标仍在缓冲区内时只移动缓冲区中的位置，否则才对内部的句柄定位一次。
+pub(crate) struct SeekBufReader<R> {
+    inner: BufReader<R>,
+    position: u64,
+}
+
+impl<R: Read> SeekBufReader<R> {
+    /// 包装一个位于开头的读取器，例如刚打开的文件
+    pub(crate) fn new(inner: R) -> SeekBufReader<R> {
+        SeekBufReader {
+            inner: BufReader::new(inner),
+            position: 0,
+        }
+    }
+}
+
+impl<R> SeekBufReader<R> {
+    pub(crate) fn get_ref(&self) -> &R {
+        self.inner.get_ref()
+    }
+
+    /// 取回内部的读取器，缓冲区中尚未读取的数据会被丢弃
+    #[cfg(feature = "memmap2")]
+    pub(crate) fn into_inner(self) -> R {
+        self.inner.into_inner()
+    }
+}
+
+impl<R: Read> Read for SeekBufReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let bytes_read = self.inner.read(buf)?;
+        self.position += bytes_read as u64;
+        Ok(bytes_read)
+    }
+}
+
+impl<R: Read> BufRead for SeekBufReader<R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        self.inner.fill_buf()
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.inner.consume(amt);
+        self.position += amt as u64;
+    }
+}
+
+impl<R: Seek> Seek for SeekBufReader<R> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let target = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::Current(offset) => self.position.checked_add_signed(offset),
+            SeekFrom::End(_) => None,
+        };
+        let relative = target.and_then(|target| {
+            let delta = i64::try_from(target as i128 - self.position as i128).ok()?;
+            Some((target, delta))
+        });
+        match relative {
+            Some((target, delta)) => {
+                self.inner.seek_relative(delta)?;
+                self.position = target;
+            }
+            // 相对于末尾的定位无法换算，交给 `BufReader` 并以其结果为准
+            None => self.position = self.inner.seek(pos)?,
+        }
+        Ok(self.position)
+    }
+}
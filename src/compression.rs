@@ -0,0 +1,75 @@
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::sync::atomic::AtomicBool;
+
+use flate2::read::DeflateDecoder;
+
+use crate::parse::read_int32;
+use crate::{copy_stream_data, MpkgEntry, MpkgError};
+
+/// 解压一个压缩的数据块并写入 `output`，解压后的长度必须与块末尾记录的一致，返回该长度
+///
+/// 数据块由deflate流与紧随其后的4字节解压后长度组成，`entry.size` 为整个数据块的大小。
+/// 调用前 `input` 应位于数据块起始处，返回时位于数据块末尾。
+pub(crate) fn inflate_entry<R, W, F>(
+    input: &mut R,
+    output: &mut W,
+    entry: &MpkgEntry,
+    buffer_size: usize,
+    cancel: Option<&AtomicBool>,
+    on_copied: F,
+) -> Result<u64, MpkgError>
+where
+    R: Read + Seek,
+    W: Write,
+    F: FnMut(u64),
+{
+    let Some(compressed_len) = entry.size.checked_sub(4) else {
+        return Err(MpkgError::DataTruncated {
+            expected: 4,
+            got: entry.size,
+            offset: entry.offset + entry.size,
+        });
+    };
+
+    // 先读取块末尾记录的解压后长度
+    input.seek(SeekFrom::Start(entry.offset + compressed_len))?;
+    let expected = read_int32(input)? as u64;
+    input.seek(SeekFrom::Start(entry.offset))?;
+
+    let mut compressed = input.take(compressed_len);
+    let mut decoder = DeflateDecoder::new(&mut compressed);
+    match copy_stream_data(
+        &mut decoder,
+        output,
+        entry.offset,
+        expected,
+        buffer_size,
+        cancel,
+        on_copied,
+    ) {
+        Err(MpkgError::DataTruncated { got, .. }) => {
+            return Err(MpkgError::SizeMismatch {
+                name: entry.name.clone(),
+                expected,
+                got,
+                offset: entry.offset,
+            });
+        }
+        result => result?,
+    };
+
+    // 解压出的数据多于记录的长度同样视为错误
+    let extra_bytes = io::copy(&mut decoder, &mut io::sink())?;
+    if extra_bytes > 0 {
+        return Err(MpkgError::SizeMismatch {
+            name: entry.name.clone(),
+            expected,
+            got: expected + extra_bytes,
+            offset: entry.offset,
+        });
+    }
+
+    // 定位到数据块末尾，跳过deflate流之后未读取的部分与长度字段
+    input.seek(SeekFrom::Start(entry.offset + entry.size))?;
+    Ok(expected)
+}
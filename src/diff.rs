@@ -0,0 +1,140 @@
+use std::collections::BTreeMap;
+use std::fmt::{self, Write};
+use std::path::Path;
+
+use crate::json::push_string;
+use crate::{list_mpkg, MpkgEntry, MpkgError};
+
+/// 两个MPKG文件之间同名但内容不同的文件
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChangedEntry {
+    /// 旧包中的文件表项
+    pub before: MpkgEntry,
+    /// 新包中的文件表项
+    pub after: MpkgEntry,
+}
+
+/// 按文件名比较两个MPKG文件表得到的差异，各列表均按文件名排序
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ArchiveDiff {
+    /// 只存在于新包中的文件
+    pub added: Vec<MpkgEntry>,
+    /// 只存在于旧包中的文件
+    pub removed: Vec<MpkgEntry>,
+    /// 两个包中都存在但大小（或CRC32）不同的文件
+    pub changed: Vec<ChangedEntry>,
+}
+
+impl ArchiveDiff {
+    /// 两个包的文件表是否没有差异
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+
+    /// 将差异序列化为JSON
+    pub fn to_json(&self) -> String {
+        let mut json = String::new();
+        json.push_str("{\n  \"added\": [");
+        push_entries(&mut json, &self.added);
+        json.push_str("],\n  \"removed\": [");
+        push_entries(&mut json, &self.removed);
+        json.push_str("],\n  \"changed\": [");
+        for (i, change) in self.changed.iter().enumerate() {
+            json.push_str(if i == 0 { "\n" } else { ",\n" });
+            json.push_str("    {\"name\": ");
+            push_string(&mut json, &change.after.name);
+            let _ = write!(
+                json,
+                ", \"old_size\": {}, \"new_size\": {}, \"old_crc32\": {}, \"new_crc32\": {}}}",
+                change.before.size, change.after.size, change.before.extra, change.after.extra
+            );
+        }
+        if !self.changed.is_empty() {
+            json.push_str("\n  ");
+        }
+        json.push_str("]\n}\n");
+        json
+    }
+}
+
+/// 以 `{"name": ..., "size": ...}` 的形式追加文件列表
+fn push_entries(json: &mut String, entries: &[MpkgEntry]) {
+    for (i, entry) in entries.iter().enumerate() {
+        json.push_str(if i == 0 { "\n" } else { ",\n" });
+        json.push_str("    {\"name\": ");
+        push_string(json, &entry.name);
+        let _ = write!(json, ", \"size\": {}}}", entry.size);
+    }
+    if !entries.is_empty() {
+        json.push_str("\n  ");
+    }
+}
+
+impl fmt::Display for ArchiveDiff {
+    /// 每行一个文件：`+` 为新增，`-` 为删除，`~` 为改变
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for entry in &self.added {
+            writeln!(f, "+ {} ({} 字节)", entry.name, entry.size)?;
+        }
+        for entry in &self.removed {
+            writeln!(f, "- {} ({} 字节)", entry.name, entry.size)?;
+        }
+        for change in &self.changed {
+            if change.before.size == change.after.size {
+                writeln!(
+                    f,
+                    "~ {} (CRC32 {:08x} -> {:08x})",
+                    change.after.name, change.before.extra, change.after.extra
+                )?;
+            } else {
+                writeln!(
+                    f,
+                    "~ {} ({} -> {} 字节)",
+                    change.after.name, change.before.size, change.after.size
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 按文件名与大小比较两个MPKG文件的文件表，不读取任何文件数据
+///
+/// 同一个包内的同名文件只取最后一个，与解包时默认的重名处理一致。
+pub fn diff_archives<P: AsRef<Path>>(a: P, b: P) -> Result<ArchiveDiff, MpkgError> {
+    Ok(diff_tables(list_mpkg(a)?, list_mpkg(b)?, false))
+}
+
+/// 与 `diff_archives` 相同，但大小相同时还比较未知字段中记录的CRC32
+///
+/// 只适用于未知字段保存CRC32的包（即可用 `verify` 选项解包的包）。
+pub fn diff_archives_with_crc<P: AsRef<Path>>(a: P, b: P) -> Result<ArchiveDiff, MpkgError> {
+    Ok(diff_tables(list_mpkg(a)?, list_mpkg(b)?, true))
+}
+
+fn diff_tables(before: Vec<MpkgEntry>, after: Vec<MpkgEntry>, compare_crc: bool) -> ArchiveDiff {
+    let by_name = |entries: Vec<MpkgEntry>| {
+        entries
+            .into_iter()
+            .map(|entry| (entry.name.clone(), entry))
+            .collect::<BTreeMap<_, _>>()
+    };
+    let mut before = by_name(before);
+    let after = by_name(after);
+
+    let mut diff = ArchiveDiff::default();
+    for (name, entry) in after {
+        match before.remove(&name) {
+            None => diff.added.push(entry),
+            Some(old) if old.size != entry.size || (compare_crc && old.extra != entry.extra) => {
+                diff.changed.push(ChangedEntry {
+                    before: old,
+                    after: entry,
+                })
+            }
+            Some(_) => {}
+        }
+    }
+    diff.removed = before.into_values().collect();
+    diff
+}
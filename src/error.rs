@@ -0,0 +1,245 @@
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+
+/// 解包过程中可能出现的错误
+#[derive(Debug)]
+pub enum MpkgError {
+    /// 底层I/O错误
+    Io(io::Error),
+    /// 头部无效，文件可能不是MPKG格式，`offset` 为出错的字段在文件中的起始位置
+    BadHeader { detail: String, offset: u64 },
+    /// 文件表在读取完成前提前结束，`offset` 为读取失败的字段在文件中的起始位置
+    TruncatedEntryTable { offset: u64 },
+    /// 文件名长度超出了文件剩余部分或允许的上限，`offset` 为该文件表项在文件中的起始位置
+    NameTooLong { length: u64, offset: u64 },
+    /// 文件数据提前结束，`offset` 为数据结束处在文件中的位置
+    DataTruncated {
+        expected: u64,
+        got: u64,
+        offset: u64,
+    },
+    /// 文件名会被写到输出目录之外
+    /// `offset` 为文件名在文件表中的位置
+    UnsafePath { name: String, offset: u64 },
+    /// 文件名为空或以目录分隔符结尾，不对应任何文件
+    /// `offset` 为文件名在文件表中的位置
+    BadEntryName { name: String, offset: u64 },
+    /// 文件名包含Windows上不可用的名称，且处理策略要求报错
+    /// `offset` 为文件名在文件表中的位置
+    ReservedName { name: String, offset: u64 },
+    /// 头部或文件名无法按指定编码解码，`offset` 为无效字节在文件中的位置
+    InvalidEncoding { offset: u64 },
+    /// 解包选项的取值无效
+    InvalidOptions(String),
+    /// 无法由MPKG文件名得到有效的输出文件夹名
+    BadFolderName(String),
+    /// 两个文件会写到同一输出路径，且重名策略要求报错
+    ///
+    /// 合并多个包时 `first_archive` 与 `second_archive` 为两个文件各自所在的包，解包单个包时为 `None`
+    DuplicateEntry {
+        name: String,
+        first_offset: u64,
+        second_offset: u64,
+        first_archive: Option<PathBuf>,
+        second_archive: Option<PathBuf>,
+    },
+    /// 写入输出文件时磁盘空间不足或无法继续写入
+    WriteFailed { path: PathBuf, source: io::Error },
+    /// 输出文件已存在，且覆盖策略要求报错
+    OutputExists(PathBuf),
+    /// 包内没有指定名称的文件
+    EntryNotFound(String),
+    /// 请求读取的字节范围超出了文件大小
+    RangeOutOfBounds {
+        name: String,
+        start: u64,
+        len: u64,
+        size: u64,
+    },
+    /// 不区分大小写查找时有多个文件匹配同一名称
+    AmbiguousEntry { name: String, matches: Vec<String> },
+    /// 解包被 `UnpackOptions::cancel` 取消
+    Cancelled,
+    /// 解压后的长度与数据块中记录的不一致
+    /// `offset` 为该文件数据块的起始位置
+    SizeMismatch {
+        name: String,
+        expected: u64,
+        got: u64,
+        offset: u64,
+    },
+    /// 文件内容的CRC32与文件表中记录的不一致
+    /// `offset` 为该文件数据的起始位置
+    ChecksumMismatch {
+        name: String,
+        expected: u32,
+        got: u32,
+        offset: u64,
+    },
+    /// 单个文件加上头部与文件表后超过了分卷大小上限
+    EntryTooLarge { name: String, size: u64, limit: u64 },
+    /// 文件表中的大小自相矛盾，例如数据区域与文件表重叠或超出64位偏移范围，
+    /// `offset` 为该文件数据区域的起始位置
+    CorruptTable {
+        entry: String,
+        detail: String,
+        offset: u64,
+    },
+    /// 待解包文件的总大小超过了 `max_total_size`
+    TotalSizeExceeded { total: u64, limit: u64 },
+}
+
+impl fmt::Display for MpkgError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MpkgError::Io(e) => write!(f, "I/O错误：{}", e),
+            MpkgError::BadHeader { detail, offset } => {
+                write!(f, "无效的MPKG头部：{}（位于偏移 {}）", detail, offset)
+            }
+            MpkgError::TruncatedEntryTable { offset } => {
+                write!(f, "文件表不完整：读取偏移 {} 处的字段时文件已结束", offset)
+            }
+            MpkgError::NameTooLong { length, offset } => {
+                write!(f, "文件名长度异常：{}（位于偏移 {}）", length, offset)
+            }
+            MpkgError::DataTruncated {
+                expected,
+                got,
+                offset,
+            } => {
+                write!(
+                    f,
+                    "文件提前终止，预期长度：{} 实际读取：{}（缺少 {} 字节，结束于偏移 {}）",
+                    expected,
+                    got,
+                    expected.saturating_sub(*got),
+                    offset
+                )
+            }
+            MpkgError::UnsafePath { name, offset } => {
+                write!(f, "不安全的文件路径：{}（位于偏移 {}）", name, offset)
+            }
+            MpkgError::BadEntryName { name, offset } => write!(
+                f,
+                "文件名为空或以目录分隔符结尾：{:?}（位于偏移 {}）",
+                name, offset
+            ),
+            MpkgError::ReservedName { name, offset } => {
+                write!(
+                    f,
+                    "文件名在Windows上不可用：{}（位于偏移 {}）",
+                    name, offset
+                )
+            }
+            MpkgError::InvalidEncoding { offset } => {
+                write!(f, "文件名编码无效，位于偏移 {}", offset)
+            }
+            MpkgError::InvalidOptions(detail) => write!(f, "无效的选项：{}", detail),
+            MpkgError::BadFolderName(path) => write!(f, "无法确定输出文件夹名：{}", path),
+            MpkgError::DuplicateEntry {
+                name,
+                first_offset,
+                second_offset,
+                first_archive: Some(first_archive),
+                second_archive: Some(second_archive),
+            } => write!(
+                f,
+                "重复的文件名：{} 分别位于 {} 的偏移 {} 与 {} 的偏移 {}",
+                name,
+                first_archive.display(),
+                first_offset,
+                second_archive.display(),
+                second_offset
+            ),
+            MpkgError::DuplicateEntry {
+                name,
+                first_offset,
+                second_offset,
+                ..
+            } => write!(
+                f,
+                "重复的文件名：{} 分别位于偏移 {} 与 {}",
+                name, first_offset, second_offset
+            ),
+            MpkgError::WriteFailed { path, source } => write!(
+                f,
+                "写入失败，磁盘空间可能不足：{}：{}",
+                path.display(),
+                source
+            ),
+            MpkgError::OutputExists(path) => write!(f, "输出文件已存在：{}", path.display()),
+            MpkgError::EntryNotFound(name) => write!(f, "包内没有该文件：{}", name),
+            MpkgError::RangeOutOfBounds {
+                name,
+                start,
+                len,
+                size,
+            } => write!(
+                f,
+                "读取范围超出文件大小：{} 从 {} 起 {} 字节，文件大小 {} 字节",
+                name, start, len, size
+            ),
+            MpkgError::AmbiguousEntry { name, matches } => {
+                write!(f, "有多个文件匹配 {}：{}", name, matches.join("、"))
+            }
+            MpkgError::Cancelled => write!(f, "解包已取消"),
+            MpkgError::SizeMismatch {
+                name,
+                expected,
+                got,
+                offset,
+            } => write!(
+                f,
+                "解压后长度不符：{} 预期：{} 实际：{}（数据位于偏移 {}）",
+                name, expected, got, offset
+            ),
+            MpkgError::EntryTooLarge { name, size, limit } => write!(
+                f,
+                "文件过大，无法放入分卷：{} 需要 {} 字节，上限 {} 字节",
+                name, size, limit
+            ),
+            MpkgError::CorruptTable {
+                entry,
+                detail,
+                offset,
+            } => {
+                write!(
+                    f,
+                    "文件表已损坏：{}：{}（位于偏移 {}）",
+                    entry, detail, offset
+                )
+            }
+            MpkgError::TotalSizeExceeded { total, limit } => {
+                write!(f, "待解包的文件共 {} 字节，超过上限 {} 字节", total, limit)
+            }
+            MpkgError::ChecksumMismatch {
+                name,
+                expected,
+                got,
+                offset,
+            } => write!(
+                f,
+                "CRC32校验失败：{} 预期：{:08x} 实际：{:08x}（数据位于偏移 {}）",
+                name, expected, got, offset
+            ),
+        }
+    }
+}
+
+impl Error for MpkgError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            MpkgError::Io(e) => Some(e),
+            MpkgError::WriteFailed { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for MpkgError {
+    fn from(e: io::Error) -> Self {
+        MpkgError::Io(e)
+    }
+}
@@ -0,0 +1,26 @@
+use std::io::Read;
+use std::path::Path;
+
+use sha2::{Digest, Sha256};
+
+use crate::{MpkgArchive, MpkgError, DEFAULT_BUFFER_SIZE};
+
+/// 按文件表顺序计算所有文件数据拼接后的SHA-256
+///
+/// 不包含头部与文件表，因此内容相同但重新打包的文件会得到相同的摘要。
+pub fn archive_digest<P: AsRef<Path>>(path: P) -> Result<[u8; 32], MpkgError> {
+    let archive = MpkgArchive::open(path)?;
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0u8; DEFAULT_BUFFER_SIZE];
+
+    for (_, mut data) in archive.entries() {
+        loop {
+            let bytes_read = data.read(&mut buffer)?;
+            if bytes_read == 0 {
+                break;
+            }
+            hasher.update(&buffer[..bytes_read]);
+        }
+    }
+    Ok(hasher.finalize().into())
+}
@@ -0,0 +1,76 @@
+use crate::MpkgError;
+
+/// 由头部版本字符串决定的文件表布局
+///
+/// 以 `PKGV` 开头的版本为Wallpaper Engine的原始格式；
+/// 以 `MPKG` 开头的版本在其后跟随修订号，新修订在原始格式的基础上扩展：
+///
+/// - 修订号 >= 2：文件大小字段为8字节
+/// - 修订号 >= 3：文件大小之后追加8字节的修改时间（Unix秒）
+/// - 修订号 >= 4：文件表移到所有文件数据之后，文件末尾的8字节记录文件表的起始偏移
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Layout {
+    /// 文件大小字段是否为8字节
+    pub wide_sizes: bool,
+    /// 每个文件是否记录修改时间
+    pub timestamps: bool,
+    /// 文件表是否位于文件数据之后，由文件末尾的偏移定位
+    pub trailing_index: bool,
+}
+
+impl Layout {
+    /// 根据头部版本字符串确定文件表布局
+    pub(crate) fn for_version(version: &str) -> Layout {
+        let revision = revision(version).unwrap_or(1);
+        Layout {
+            wide_sizes: revision >= 2,
+            timestamps: revision >= 3,
+            trailing_index: revision >= 4,
+        }
+    }
+}
+
+/// 解析 `MPKG` 版本字符串中的修订号，例如 `MPKG0002` 为 2
+fn revision(version: &str) -> Option<u32> {
+    let digits = version.strip_prefix("MPKG")?;
+    let end = digits
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(digits.len());
+    digits[..end].parse().ok()
+}
+
+/// 文件表位于末尾时，文件末尾记录文件表起始偏移的字段长度
+pub(crate) const FOOTER_LEN: u64 = 8;
+
+/// 未知字段中标记数据块为deflate压缩的位
+///
+/// 压缩的数据块由deflate流与紧随其后的4字节解压后长度组成。
+pub(crate) const COMPRESSED_FLAG: u32 = 0x1;
+
+/// 可识别的头部版本前缀
+pub(crate) const SIGNATURES: &[&str] = &["PKGV", "MPKG"];
+
+/// 检查头部是否以可识别的版本前缀开头，避免把任意文件当作MPKG解析
+pub(crate) fn check_signature(header: &[u8]) -> Result<(), MpkgError> {
+    if SIGNATURES
+        .iter()
+        .any(|signature| header.starts_with(signature.as_bytes()))
+    {
+        return Ok(());
+    }
+    let found = header
+        .iter()
+        .take(8)
+        .map(|byte| format!("{:02x}", byte))
+        .collect::<Vec<_>>()
+        .join(" ");
+    // 版本标识紧跟在4字节的头部长度之后
+    Err(MpkgError::BadHeader {
+        detail: format!(
+            "未知的版本标识 [{}]，应以 {} 开头",
+            found,
+            SIGNATURES.join(" 或 ")
+        ),
+        offset: 4,
+    })
+}
@@ -0,0 +1,47 @@
+/// 用通配符匹配包内文件名
+///
+/// 支持 `*`（匹配除 `/` 外的任意字符）、`?`（匹配除 `/` 外的单个字符）
+/// 与 `**`（匹配包括 `/` 在内的任意字符）。
+/// 不含 `/` 的模式只与文件名的最后一部分比较，因此 `*.png` 会匹配任意目录下的PNG文件。
+pub(crate) fn glob_match(pattern: &str, name: &str) -> bool {
+    let name = if pattern.contains('/') {
+        name
+    } else {
+        name.rsplit('/').next().unwrap_or(name)
+    };
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    match_from(&pattern, &name)
+}
+
+fn match_from(pattern: &[char], name: &[char]) -> bool {
+    match pattern.first() {
+        None => name.is_empty(),
+        Some('*') if pattern.get(1) == Some(&'*') => {
+            let rest = &pattern[2..];
+            // `**/` 也可以匹配零层目录
+            if let Some(after_slash) = rest.strip_prefix(&['/']) {
+                if match_from(after_slash, name) {
+                    return true;
+                }
+            }
+            (0..=name.len()).any(|i| match_from(rest, &name[i..]))
+        }
+        Some('*') => {
+            let rest = &pattern[1..];
+            for i in 0..=name.len() {
+                if match_from(rest, &name[i..]) {
+                    return true;
+                }
+                if name.get(i) == Some(&'/') {
+                    break;
+                }
+            }
+            false
+        }
+        Some('?') => {
+            matches!(name.first(), Some(c) if *c != '/') && match_from(&pattern[1..], &name[1..])
+        }
+        Some(c) => name.first() == Some(c) && match_from(&pattern[1..], &name[1..]),
+    }
+}
@@ -0,0 +1,176 @@
+use std::fs::File;
+use std::io::{self, BufReader, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+use flate2::read::GzDecoder;
+
+use crate::parse::{read_header, read_leading_table, ArchiveTable};
+use crate::{
+    output_folder, unpack_stream, MpkgError, ProgressEvent, StreamSource, UnpackOptions,
+    UnpackReport,
+};
+
+/// gzip流开头的魔数
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// 只能向前读取的流，向前定位时读取并丢弃中间的数据
+///
+/// 供顺序解包使用：跳过的文件仍会被完整读取（解压），只是不写出。
+pub(crate) struct ForwardReader<R> {
+    inner: R,
+    position: u64,
+}
+
+impl<R: Read> Read for ForwardReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let bytes_read = self.inner.read(buf)?;
+        self.position += bytes_read as u64;
+        Ok(bytes_read)
+    }
+}
+
+impl<R: Read> Seek for ForwardReader<R> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let target = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::Current(offset) => self.position.checked_add_signed(offset),
+            SeekFrom::End(_) => None,
+        };
+        let Some(target) = target.filter(|&target| target >= self.position) else {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "顺序读取的流只能向前定位",
+            ));
+        };
+        // 超出流末尾时与文件一样允许定位，之后的读取返回EOF
+        io::copy(
+            &mut (&mut self.inner).take(target - self.position),
+            &mut io::sink(),
+        )?;
+        self.position = target;
+        Ok(target)
+    }
+}
+
+/// 打开gzip压缩的MPKG文件，返回解压后的只能向前读取的流
+pub(crate) fn open_gzip(path: &Path) -> io::Result<ForwardReader<BufReader<GzDecoder<File>>>> {
+    Ok(ForwardReader {
+        inner: BufReader::new(GzDecoder::new(File::open(path)?)),
+        position: 0,
+    })
+}
+
+/// 判断文件是否以gzip魔数开头，不足两个字节的文件不是gzip文件
+pub(crate) fn is_gzip(path: &Path) -> io::Result<bool> {
+    let mut magic = [0u8; 2];
+    match File::open(path)?.read_exact(&mut magic) {
+        Ok(()) => Ok(magic == GZIP_MAGIC),
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => Ok(false),
+        Err(e) => Err(e),
+    }
+}
+
+/// 读取gzip压缩的MPKG文件的头部与文件表
+///
+/// 解压后的长度只能读完整个流才能得知，因此会解压一遍全部数据，使文件长度与数据范围
+/// 与未压缩的文件一样准确。
+pub(crate) fn read_gzip_table(
+    path: &Path,
+    options: &UnpackOptions,
+) -> Result<ArchiveTable, MpkgError> {
+    let mut reader = open_gzip(path)?;
+    let mut table = read_leading_table(&mut reader, options)?;
+    let archive_len = table.data_start + io::copy(&mut reader, &mut io::sink())?;
+    table.archive_len = archive_len;
+    table.data_limit = archive_len;
+    Ok(table)
+}
+
+/// 去掉文件名末尾的 `.gz`（不区分大小写），例如 `a.mpkg.gz` -> `a.mpkg`
+fn strip_gz_extension(path: &Path) -> PathBuf {
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some(ext) if ext.eq_ignore_ascii_case("gz") => path.with_extension(""),
+        _ => path.to_path_buf(),
+    }
+}
+
+/// 解包gzip压缩的MPKG文件（如 `.mpkg.gz`），输出文件夹按去掉 `.gz` 之后的文件名命名
+///
+/// 解压流无法定位，因此按文件表顺序读取全部数据：筛选、`resume` 等跳过的文件仍会被读取并丢弃，
+/// 不会比完整解包更快。文件表位于末尾的格式（`MPKG0004`）、包内压缩的文件与并行解包均不受支持。
+pub(crate) fn unpack_gzip_file<F>(
+    input_file: &Path,
+    output_dir: &Path,
+    options: &UnpackOptions,
+    on_progress: F,
+) -> Result<UnpackReport, MpkgError>
+where
+    F: FnMut(ProgressEvent),
+{
+    let name = strip_gz_extension(input_file);
+    let unpacked_folder = output_folder(&name, output_dir, options, || {
+        read_header(&mut open_gzip(input_file)?, None, options)
+    })?;
+    unpack_stream(
+        &mut open_gzip(input_file)?,
+        StreamSource::Sequential,
+        &unpacked_folder,
+        options,
+        on_progress,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::io::Write;
+
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+
+    use crate::tests::{quiet, ArchiveBuilder};
+    use crate::{archive_info, list_mpkg, validate_mpkg_with_options, MpkgError};
+
+    /// 把 `bytes` 压缩后写入 `path`
+    fn write_gzip(path: &std::path::Path, bytes: &[u8]) {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(bytes).unwrap();
+        fs::write(path, encoder.finish().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn list_info_and_verify_gzip() {
+        let bytes = ArchiveBuilder::new("PKGV0001")
+            .file("a.txt", b"hello")
+            .file("b/c.txt", b"world")
+            .build();
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("a.mpkg.gz");
+        write_gzip(&path, &bytes);
+
+        let names: Vec<_> = list_mpkg(&path)
+            .unwrap()
+            .into_iter()
+            .map(|entry| entry.name)
+            .collect();
+        assert_eq!(names, ["a.txt", "b/c.txt"]);
+
+        let info = archive_info(&path).unwrap();
+        assert_eq!(info.file_count, 2);
+        assert_eq!(info.archive_len, bytes.len() as u64);
+        assert!(info.sizes_match);
+
+        let report = validate_mpkg_with_options(&path, &quiet()).unwrap();
+        assert_eq!((report.file_count, report.trailing_bytes), (2, 0));
+
+        // 解压后的数据不足时与未压缩的文件一样报告数据不完整
+        let truncated = dir.path().join("b.mpkg.gz");
+        write_gzip(&truncated, &bytes[..bytes.len() - 2]);
+        let result = validate_mpkg_with_options(&truncated, &quiet());
+        assert!(
+            matches!(result, Err(MpkgError::DataTruncated { .. })),
+            "{:?}",
+            result
+        );
+    }
+}
@@ -0,0 +1,104 @@
+use std::fmt::Write;
+use std::path::Path;
+
+use crate::manifest::index_tsv;
+use crate::{read_path_table, MpkgEntry, MpkgError, UnpackOptions};
+
+/// 将字符串转义为JSON字符串字面量并追加到 `out`
+pub(crate) fn push_string(out: &mut String, value: &str) {
+    out.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+/// `list_mpkg_json` 输出的格式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ListFormat {
+    /// 一个JSON对象，包含格式版本、统计信息与 `entries` 数组
+    #[default]
+    Json,
+    /// 每行一个JSON对象，对应文件表中的一项
+    Ndjson,
+    /// 每行 `name\toffset\tsize`，与 `index.tsv` 的内容相同
+    Tsv,
+}
+
+/// 以单行JSON对象的形式追加文件表中的一项，`archive` 不为 `None` 时附带所属的MPKG文件
+fn push_entry(out: &mut String, entry: &MpkgEntry, archive: Option<&str>) {
+    out.push('{');
+    if let Some(archive) = archive {
+        out.push_str("\"archive\": ");
+        push_string(out, archive);
+        out.push_str(", ");
+    }
+    let _ = write!(out, "\"index\": {}, \"name\": ", entry.index);
+    push_string(out, &entry.name);
+    let _ = write!(
+        out,
+        ", \"size\": {}, \"offset\": {}, \"extra\": {}, \"modified\": ",
+        entry.size, entry.offset, entry.extra
+    );
+    match entry.modified {
+        Some(modified) => {
+            let _ = write!(out, "{}", modified);
+        }
+        None => out.push_str("null"),
+    }
+    out.push('}');
+}
+
+/// 以JSON列出MPKG文件的文件表，供脚本读取
+///
+/// `Json` 输出一个对象，包含 `archive`、`version`、`file_count`、`total_bytes` 与 `entries`；
+/// `Ndjson` 每行输出一项，每项都带有 `archive`。各项包含 `index`、`name`、`size`、`offset`、
+/// `extra` 与 `modified`（未记录时为 `null`）。`Tsv` 不是JSON，而是与 `index.tsv` 相同的索引。
+/// 输出以换行结尾。
+pub fn list_mpkg_json<P: AsRef<Path>>(path: P, format: ListFormat) -> Result<String, MpkgError> {
+    let path = path.as_ref();
+    let archive = path.to_string_lossy();
+    let table = read_path_table(path, &UnpackOptions::default())?;
+
+    if format == ListFormat::Tsv {
+        return Ok(index_tsv(&table.entries));
+    }
+    let mut json = String::new();
+    if format == ListFormat::Ndjson {
+        for entry in &table.entries {
+            push_entry(&mut json, entry, Some(&archive));
+            json.push('\n');
+        }
+        return Ok(json);
+    }
+    json.push_str("{\n  \"archive\": ");
+    push_string(&mut json, &archive);
+    json.push_str(",\n  \"version\": ");
+    push_string(&mut json, &table.version);
+    let total_bytes: u64 = table.entries.iter().map(|entry| entry.size).sum();
+    let _ = write!(
+        json,
+        ",\n  \"file_count\": {},\n  \"total_bytes\": {},\n  \"entries\": [",
+        table.entries.len(),
+        total_bytes
+    );
+    for (i, entry) in table.entries.iter().enumerate() {
+        json.push_str(if i == 0 { "\n    " } else { ",\n    " });
+        push_entry(&mut json, entry, None);
+    }
+    if !table.entries.is_empty() {
+        json.push_str("\n  ");
+    }
+    json.push_str("]\n}\n");
+    Ok(json)
+}
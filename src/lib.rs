@@ -0,0 +1,2257 @@
+//! Wallpaper Engine 移动端资源包 ( .mpkg ) 解包库
+
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+use std::ffi::{OsStr, OsString};
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Cursor, Read, Seek, SeekFrom, Write};
+use std::path::{Component, Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{mpsc, Mutex};
+use std::thread;
+use std::time::{Duration, UNIX_EPOCH};
+
+mod archive;
+#[cfg(feature = "tokio")]
+mod async_unpack;
+mod buffered;
+#[cfg(feature = "compression")]
+mod compression;
+mod crc32;
+mod diff;
+mod error;
+#[cfg(feature = "sha2")]
+mod fingerprint;
+mod format;
+mod glob;
+#[cfg(feature = "compression")]
+mod gzip;
+mod json;
+mod manifest;
+#[cfg(feature = "memmap2")]
+mod mapping;
+mod options;
+mod pack;
+mod parse;
+mod progress;
+mod sanitize;
+mod sink;
+mod staging;
+#[cfg(feature = "tar")]
+mod tarball;
+mod targets;
+
+pub use archive::{BoundedReader, Entries, EntryReader, MpkgArchive};
+#[cfg(feature = "tokio")]
+pub use async_unpack::unpack_mpkg_async;
+pub use diff::{diff_archives, diff_archives_with_crc, ArchiveDiff, ChangedEntry};
+pub use error::MpkgError;
+#[cfg(feature = "sha2")]
+pub use fingerprint::archive_digest;
+pub use json::{list_mpkg_json, ListFormat};
+pub use manifest::{INDEX_FILE_NAME, MANIFEST_FILE_NAME};
+#[cfg(feature = "memmap2")]
+pub use mapping::map_entry;
+pub use options::{
+    DuplicatePolicy, FilenameEncoding, LogLevel, OverwritePolicy, RenameHook, ReservedNamePolicy,
+    SizeOrder, UnpackOptions, UnpackOptionsBuilder, DEFAULT_BUFFER_SIZE, DEFAULT_EXTENSIONS,
+    DEFAULT_MAX_FILE_COUNT, DEFAULT_MAX_HEADER_LENGTH, DEFAULT_MAX_MAP_SIZE,
+    DEFAULT_MAX_NAME_LENGTH,
+};
+pub use pack::{
+    merge_mpkg, pack_mpkg, pack_mpkg_dry_run, pack_mpkg_from_entries, pack_mpkg_with_table,
+    split_mpkg, PackLayout, DEFAULT_VERSION,
+};
+pub use parse::{read_header_info, read_header_version, HeaderInfo, MpkgEntry};
+pub use progress::{print_progress, DirProgressEvent, ProgressEvent};
+pub use sink::{unpack_to_map, unpack_to_map_with_options, unpack_to_sink, FsSink, OutputSink};
+#[cfg(feature = "tar")]
+pub use tarball::repack_to_tar;
+
+use buffered::SeekBufReader;
+use crc32::Crc32Writer;
+#[cfg(feature = "compression")]
+use gzip::{is_gzip, open_gzip, read_gzip_table, unpack_gzip_file};
+use manifest::{write_index, write_manifest};
+#[cfg(feature = "compression")]
+use parse::read_leading_table;
+use parse::{read_archive_table, read_header, ArchiveTable};
+use sanitize::sanitize_entry_path;
+use staging::{commit_folder, partial_folder};
+use targets::{check_total_size, is_excluded, plan_targets};
+
+/// 复制流中的数据到目标流，每写入一块数据就以已复制的字节数调用 `on_copied`，返回实际写入的字节数
+///
+/// 由 `input.take(length)` 经 `io::copy` 复制，读取缓冲区最大为 `buffer_size`，数据较短时只分配所需的大小。
+/// 每复制一块数据前检查一次 `cancel`，被置位时返回 `MpkgError::Cancelled`；
+/// 输入在 `length` 字节之前结束时返回 `MpkgError::DataTruncated`，`offset` 为数据在文件中的起始位置，
+/// 只用于报告错误。
+fn copy_stream_data<R: Read, W: Write, F: FnMut(u64)>(
+    input: &mut R,
+    output: &mut W,
+    offset: u64,
+    length: u64,
+    buffer_size: usize,
+    cancel: Option<&AtomicBool>,
+    on_copied: F,
+) -> Result<u64, MpkgError> {
+    let capacity = buffer_size.min(usize::try_from(length).unwrap_or(usize::MAX));
+    let mut source = BufReader::with_capacity(capacity, input.take(length));
+    let mut sink = ProgressWriter {
+        inner: output,
+        written: 0,
+        cancel,
+        cancelled: false,
+        on_copied,
+    };
+
+    let copied = match io::copy(&mut source, &mut sink) {
+        Err(_) if sink.cancelled => return Err(MpkgError::Cancelled),
+        result => result?,
+    };
+    if copied != length {
+        return Err(MpkgError::DataTruncated {
+            expected: length,
+            got: copied,
+            offset: offset + copied,
+        });
+    }
+    Ok(copied)
+}
+
+/// 每写入一块数据就报告已写入的字节数，并在写入下一块之前检查取消标志
+struct ProgressWriter<'a, W, F> {
+    inner: &'a mut W,
+    written: u64,
+    cancel: Option<&'a AtomicBool>,
+    /// 因取消标志而中止写入，用于把返回的I/O错误还原为 `MpkgError::Cancelled`
+    cancelled: bool,
+    on_copied: F,
+}
+
+impl<W: Write, F: FnMut(u64)> Write for ProgressWriter<'_, W, F> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if check_cancelled(self.cancel).is_err() {
+            self.cancelled = true;
+            return Err(io::Error::other("解包已取消"));
+        }
+        self.inner.write_all(buf)?;
+        self.written += buf.len() as u64;
+        (self.on_copied)(self.written);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// `cancel` 被置位时返回 `MpkgError::Cancelled`
+fn check_cancelled(cancel: Option<&AtomicBool>) -> Result<(), MpkgError> {
+    match cancel {
+        Some(flag) if flag.load(Ordering::Relaxed) => Err(MpkgError::Cancelled),
+        _ => Ok(()),
+    }
+}
+
+/// 将一个文件的数据块写入 `output`，`input` 应位于数据块起始处，返回写入的字节数
+///
+/// 启用 `decompress` 且文件被标记为压缩时先解压再写入，否则原样复制。
+fn copy_entry_data<R, W, F>(
+    input: &mut R,
+    output: &mut W,
+    entry: &MpkgEntry,
+    options: &UnpackOptions,
+    on_copied: F,
+) -> Result<u64, MpkgError>
+where
+    R: Read + Seek,
+    W: Write,
+    F: FnMut(u64),
+{
+    let cancel = options.cancel.as_deref();
+    #[cfg(feature = "compression")]
+    if options.inflates(entry) {
+        return compression::inflate_entry(
+            input,
+            output,
+            entry,
+            options.buffer_size,
+            cancel,
+            on_copied,
+        );
+    }
+    copy_stream_data(
+        input,
+        output,
+        entry.offset,
+        entry.size,
+        options.buffer_size,
+        cancel,
+        on_copied,
+    )
+}
+
+/// 只读取文件开头的头部，判断其是否像MPKG文件，是则返回格式版本字符串
+///
+/// 不读取文件表，也不使用额外的缓冲区，适合快速筛选大量未知文件；
+/// 无法打开、头部无效或版本前缀无法识别时返回 `None`。
+pub fn peek_header<P: AsRef<Path>>(path: P) -> Option<String> {
+    let mut file = File::open(path).ok()?;
+    let archive_len = file.metadata().ok()?.len();
+    read_header(&mut file, Some(archive_len), &UnpackOptions::default()).ok()
+}
+
+/// 打开MPKG文件并读取头部与文件表
+fn open_archive(
+    path: &Path,
+    options: &UnpackOptions,
+) -> Result<(SeekBufReader<File>, ArchiveTable), MpkgError> {
+    let mut input_stream = SeekBufReader::new(File::open(path)?);
+    let table = read_archive_table(&mut input_stream, options)?;
+    Ok((input_stream, table))
+}
+
+/// 读取MPKG文件的头部与文件表，启用 `compression` 特性时也接受gzip压缩的MPKG文件
+fn read_path_table(path: &Path, options: &UnpackOptions) -> Result<ArchiveTable, MpkgError> {
+    #[cfg(feature = "compression")]
+    if is_gzip(path)? {
+        return read_gzip_table(path, options);
+    }
+    let (_, table) = open_archive(path, options)?;
+    Ok(table)
+}
+
+/// 列出MPKG文件中的文件表，不解包任何文件
+///
+/// 启用 `compression` 特性时也可以列出gzip压缩的MPKG文件，例如 `a.mpkg.gz`。
+pub fn list_mpkg<P: AsRef<Path>>(path: P) -> Result<Vec<MpkgEntry>, MpkgError> {
+    list_mpkg_with_options(path, &UnpackOptions::default())
+}
+
+/// 按给定选项列出MPKG文件中的文件表，不解包任何文件
+pub fn list_mpkg_with_options<P: AsRef<Path>>(
+    path: P,
+    options: &UnpackOptions,
+) -> Result<Vec<MpkgEntry>, MpkgError> {
+    Ok(read_path_table(path.as_ref(), options)?.entries)
+}
+
+/// 列出内存中整个MPKG文件的文件表
+pub fn list_mpkg_from_bytes(data: &[u8]) -> Result<Vec<MpkgEntry>, MpkgError> {
+    let table = read_archive_table(&mut Cursor::new(data), &UnpackOptions::default())?;
+    Ok(table.entries)
+}
+
+/// 按文件表顺序返回每个文件的 `(文件名, 数据偏移, 大小)`，偏移从文件开头算起
+///
+/// 偏移由文件表中的大小依次累加得到，与 `extract_file` 及 `MpkgArchive` 读取数据时使用的一致。
+pub fn entry_offsets<P: AsRef<Path>>(path: P) -> Result<Vec<(String, u64, u64)>, MpkgError> {
+    let (_, table) = open_archive(path.as_ref(), &UnpackOptions::default())?;
+    Ok(table
+        .entries
+        .into_iter()
+        .map(|entry| (entry.name, entry.offset, entry.size))
+        .collect())
+}
+
+/// MPKG文件的统计信息
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArchiveInfo {
+    /// 头部的格式版本字符串
+    pub version: String,
+    /// 文件数量
+    pub file_count: usize,
+    /// 所有文件数据的总字节数
+    pub total_bytes: u64,
+    /// 最大的文件，没有文件时为 `None`
+    pub largest: Option<MpkgEntry>,
+    /// 文件名与大小之间未知字段的各个取值及其出现次数，按出现次数从多到少排列
+    pub extra_values: Vec<(u32, usize)>,
+    /// 整个MPKG文件的长度
+    pub archive_len: u64,
+    /// 文件表描述的数据是否恰好填满整个数据区
+    pub sizes_match: bool,
+}
+
+/// 统计未知字段的各个取值，出现次数相同时按取值排列
+fn count_extra_values(entries: &[MpkgEntry]) -> Vec<(u32, usize)> {
+    let mut counts = BTreeMap::new();
+    for entry in entries {
+        *counts.entry(entry.extra).or_insert(0) += 1;
+    }
+    let mut values: Vec<_> = counts.into_iter().collect();
+    values.sort_by_key(|&(_, count)| Reverse(count));
+    values
+}
+
+/// 读取MPKG文件的统计信息，不解包也不写入任何文件
+///
+/// 与 `validate_mpkg` 不同，文件长度与文件表不符时不会报错，而是体现在 `sizes_match` 中。
+/// gzip压缩的MPKG文件按解压后的内容统计，`archive_len` 为解压后的长度。
+pub fn archive_info<P: AsRef<Path>>(path: P) -> Result<ArchiveInfo, MpkgError> {
+    let table = read_path_table(path.as_ref(), &UnpackOptions::default())?;
+    let data_end = table.data_end();
+    Ok(ArchiveInfo {
+        file_count: table.entries.len(),
+        total_bytes: table.entries.iter().map(|entry| entry.size).sum(),
+        largest: table.entries.iter().max_by_key(|entry| entry.size).cloned(),
+        extra_values: count_extra_values(&table.entries),
+        archive_len: table.archive_len,
+        sizes_match: data_end == table.data_limit,
+        version: table.version,
+    })
+}
+
+/// 校验MPKG文件得到的报告
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MpkgReport {
+    /// 头部的格式版本字符串
+    pub version: String,
+    /// 文件数量
+    pub file_count: usize,
+    /// 所有文件数据的总字节数
+    pub total_bytes: u64,
+    /// 最后一个文件数据之后、文件末尾（或位于末尾的文件表）之前多余的字节数
+    pub trailing_bytes: u64,
+}
+
+/// 校验MPKG文件的完整性，不写入任何文件
+///
+/// 检查文件名是否有效且安全，以及每个文件的数据是否都完整地位于文件内。
+pub fn validate_mpkg<P: AsRef<Path>>(path: P) -> Result<MpkgReport, MpkgError> {
+    let options = UnpackOptions::default();
+    let table = read_path_table(path.as_ref(), &options)?;
+    validate_table(&table, &options)
+}
+
+/// 与 `validate_mpkg` 相同，并且按给定选项读取每个文件的全部数据，不写入任何文件
+///
+/// 数据读取失败、压缩的文件无法解压，或启用 `verify` 时CRC32不符，都会返回对应的错误。
+/// gzip压缩的MPKG文件按顺序读取，与解包时一样不支持包内压缩的文件。
+pub fn validate_mpkg_with_options<P: AsRef<Path>>(
+    path: P,
+    options: &UnpackOptions,
+) -> Result<MpkgReport, MpkgError> {
+    options.validate()?;
+    let path = path.as_ref();
+    #[cfg(feature = "compression")]
+    if is_gzip(path)? {
+        let table = read_gzip_table(path, options)?;
+        let report = validate_table(&table, options)?;
+        read_all_entries(&mut open_gzip(path)?, &table, options)?;
+        return Ok(report);
+    }
+    let (mut input_stream, table) = open_archive(path, options)?;
+    let report = validate_table(&table, options)?;
+    read_all_entries(&mut input_stream, &table, options)?;
+    Ok(report)
+}
+
+/// 按文件表顺序读取每个文件的全部数据并丢弃，检查数据是否完整
+fn read_all_entries<R: Read + Seek>(
+    input_stream: &mut R,
+    table: &ArchiveTable,
+    options: &UnpackOptions,
+) -> Result<(), MpkgError> {
+    for entry in &table.entries {
+        check_cancelled(options.cancel.as_deref())?;
+        input_stream.seek(SeekFrom::Start(entry.offset))?;
+        write_entry_data(input_stream, &mut io::sink(), entry, options, |_| {})?;
+    }
+    Ok(())
+}
+
+/// 检查文件名是否有效且安全，以及每个文件的数据是否都完整地位于文件内
+fn validate_table(table: &ArchiveTable, options: &UnpackOptions) -> Result<MpkgReport, MpkgError> {
+    // 文件数据依次存放，数据结束位置为最后一个文件之后
+    let mut data_end = table.data_start;
+    for entry in &table.entries {
+        sanitize_entry_path(&entry.name, entry.name_offset, options)?;
+        let available = table.data_limit.saturating_sub(entry.offset);
+        if entry.size > available {
+            return Err(MpkgError::DataTruncated {
+                expected: entry.size,
+                got: available,
+                offset: table.data_limit,
+            });
+        }
+        data_end = entry.offset + entry.size;
+    }
+
+    Ok(MpkgReport {
+        version: table.version.clone(),
+        file_count: table.entries.len(),
+        total_bytes: table.entries.iter().map(|entry| entry.size).sum(),
+        trailing_bytes: table.data_limit - data_end,
+    })
+}
+
+/// 按名称查找文件；`ignore_case` 为真时完全一致的名称优先，否则要求不区分大小写时唯一匹配
+fn find_entry<'a>(
+    entries: &'a [MpkgEntry],
+    name: &str,
+    ignore_case: bool,
+) -> Result<Option<&'a MpkgEntry>, MpkgError> {
+    if let Some(entry) = entries.iter().find(|entry| entry.name == name) {
+        return Ok(Some(entry));
+    }
+    if !ignore_case {
+        return Ok(None);
+    }
+    let lowercase = name.to_lowercase();
+    let mut matches = entries
+        .iter()
+        .filter(|entry| entry.name.to_lowercase() == lowercase);
+    let first = matches.next();
+    if let Some(second) = matches.next() {
+        let mut names = vec![first.unwrap().name.clone(), second.name.clone()];
+        names.extend(matches.map(|entry| entry.name.clone()));
+        return Err(MpkgError::AmbiguousEntry {
+            name: name.to_string(),
+            matches: names,
+        });
+    }
+    Ok(first)
+}
+
+/// 从MPKG文件中提取名为 `entry_name` 的单个文件写入 `out`
+///
+/// 文件名需与包内存储的名称完全一致，没有匹配的文件时返回 `false`。
+pub fn extract_file<P, W>(archive: P, entry_name: &str, out: &mut W) -> Result<bool, MpkgError>
+where
+    P: AsRef<Path>,
+    W: Write,
+{
+    extract_file_with_options(archive, entry_name, out, &UnpackOptions::default())
+}
+
+/// 按给定选项从MPKG文件中提取单个文件写入 `out`，没有匹配的文件时返回 `false`
+///
+/// 启用 `ignore_case` 时不区分大小写查找：名称完全一致的文件优先，
+/// 否则多个文件同时匹配时返回 `MpkgError::AmbiguousEntry`。
+pub fn extract_file_with_options<P, W>(
+    archive: P,
+    entry_name: &str,
+    out: &mut W,
+    options: &UnpackOptions,
+) -> Result<bool, MpkgError>
+where
+    P: AsRef<Path>,
+    W: Write,
+{
+    options.validate()?;
+    let (mut input_stream, table) = open_archive(archive.as_ref(), options)?;
+    let Some(entry) = find_entry(&table.entries, entry_name, options.ignore_case)? else {
+        return Ok(false);
+    };
+
+    input_stream.seek(SeekFrom::Start(entry.offset))?;
+    copy_entry_data(&mut input_stream, out, entry, options, |_| {})?;
+    Ok(true)
+}
+
+/// 从MPKG文件中把名为 `entry_name` 的文件的 `[start, start + len)` 字节写入 `out`
+///
+/// 只定位并读取这一段数据，适合随机访问较大的文件；数据按包内存储的原样复制，不会解压。
+/// 文件名需与包内存储的名称完全一致，没有匹配的文件时返回 `false`，
+/// 范围超出文件大小时返回 `MpkgError::RangeOutOfBounds`。
+pub fn extract_file_range<P, W>(
+    archive: P,
+    entry_name: &str,
+    start: u64,
+    len: u64,
+    out: &mut W,
+) -> Result<bool, MpkgError>
+where
+    P: AsRef<Path>,
+    W: Write,
+{
+    let (mut input_stream, table) = open_archive(archive.as_ref(), &UnpackOptions::default())?;
+    let Some(entry) = find_entry(&table.entries, entry_name, false)? else {
+        return Ok(false);
+    };
+    if start.checked_add(len).is_none_or(|end| end > entry.size) {
+        return Err(MpkgError::RangeOutOfBounds {
+            name: entry.name.clone(),
+            start,
+            len,
+            size: entry.size,
+        });
+    }
+
+    input_stream.seek(SeekFrom::Start(entry.offset + start))?;
+    copy_stream_data(
+        &mut input_stream,
+        out,
+        entry.offset + start,
+        len,
+        DEFAULT_BUFFER_SIZE,
+        None,
+        |_| {},
+    )?;
+    Ok(true)
+}
+
+/// 解包的流从何而来，决定如何读取文件表以及能否并行解包
+#[derive(Debug, Clone, Copy)]
+enum StreamSource<'a> {
+    /// 从该路径打开的文件，并行解包时各线程会重新打开它
+    File(&'a Path),
+    /// 调用方传入的可定位的流
+    Reader,
+    /// 只能向前读取的流，例如gzip解压流，文件表必须位于数据之前
+    #[cfg(feature = "compression")]
+    Sequential,
+}
+
+/// 已读取头部并检查过文件名、可以开始写入的MPKG文件
+struct PreparedArchive {
+    version: String,
+    entries: Vec<MpkgEntry>,
+    /// 与 `entries` 一一对应的输出路径，`None` 表示跳过该文件
+    targets: Vec<Option<PathBuf>>,
+}
+
+/// 解包结果统计
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct UnpackReport {
+    /// 头部的格式版本字符串
+    pub version: String,
+    /// 实际解包的文件数量
+    pub files_extracted: usize,
+    /// 被跳过的文件数量，不含被 `exclude` 排除的文件
+    pub files_skipped: usize,
+    /// 被 `exclude` 排除的文件数量
+    pub files_excluded: usize,
+    /// 写入输出文件的总字节数，压缩的文件按解压后的长度计算
+    pub bytes_written: u64,
+    /// 启用 `continue_on_error` 时解包失败的文件名
+    pub files_failed: Vec<String>,
+    /// 文件写入的文件夹，启用 `atomic` 时为最终文件夹；写入 `OutputSink` 时为空路径
+    pub output_dir: PathBuf,
+}
+
+/// 读取头部与文件表，并在写入任何文件之前检查文件是否完整、所有文件名是否会跳出输出目录
+fn prepare_archive<R: Read + Seek>(
+    reader: &mut R,
+    unpacked_folder: &Path,
+    options: &UnpackOptions,
+) -> Result<PreparedArchive, MpkgError> {
+    prepare_table(
+        read_archive_table(reader, options)?,
+        unpacked_folder,
+        options,
+    )
+}
+
+/// 检查已读取的文件表是否完整，并计算每个文件的输出路径
+fn prepare_table(
+    table: ArchiveTable,
+    unpacked_folder: &Path,
+    options: &UnpackOptions,
+) -> Result<PreparedArchive, MpkgError> {
+    table.check_complete()?;
+    let ArchiveTable {
+        version, entries, ..
+    } = table;
+    let targets = plan_targets(&entries, unpacked_folder, options)?;
+    check_total_size(&entries, &targets, options)?;
+    Ok(PreparedArchive {
+        version,
+        entries,
+        targets,
+    })
+}
+
+/// 按覆盖策略判断是否写入输出文件
+fn should_write(target: &Path, policy: OverwritePolicy) -> Result<bool, MpkgError> {
+    match policy {
+        OverwritePolicy::Overwrite => Ok(true),
+        _ if !target.exists() => Ok(true),
+        OverwritePolicy::Skip => Ok(false),
+        OverwritePolicy::Error => Err(MpkgError::OutputExists(target.to_path_buf())),
+    }
+}
+
+/// 判断输出文件是否已存在且大小与文件表一致
+fn already_extracted(target: &Path, size: u64) -> bool {
+    fs::metadata(target).is_ok_and(|metadata| metadata.is_file() && metadata.len() == size)
+}
+
+/// 判断输出文件是否已存在、大小一致且内容的CRC32与未知字段记录的值相同
+///
+/// 大小不符时不读取文件；读取失败时视为已改变，交给之后的写入报告错误。
+fn unchanged(target: &Path, entry: &MpkgEntry, buffer_size: usize) -> bool {
+    if !already_extracted(target, entry.size) {
+        return false;
+    }
+    let mut checked = Crc32Writer::new(io::sink());
+    let capacity = buffer_size.min(usize::try_from(entry.size).unwrap_or(usize::MAX));
+    File::open(target)
+        .and_then(|file| io::copy(&mut BufReader::with_capacity(capacity, file), &mut checked))
+        .is_ok_and(|_| checked.crc() == entry.extra)
+}
+
+/// 检查输出文件及其在 `unpacked_folder` 内的上级文件夹都不是已存在的符号链接
+///
+/// 防止预先放置的符号链接把文件写到输出文件夹之外。
+/// `rename` 给出的绝对路径不在 `unpacked_folder` 内，检查其全部上级文件夹。
+///
+/// 出错时报告 `entry` 的文件名偏移。
+fn check_no_symlinks(
+    unpacked_folder: &Path,
+    target: &Path,
+    entry: &MpkgEntry,
+) -> Result<(), MpkgError> {
+    let inside = target.starts_with(unpacked_folder);
+    for path in target.ancestors().take_while(|path| {
+        !inside || (*path != unpacked_folder && path.starts_with(unpacked_folder))
+    }) {
+        if fs::symlink_metadata(path).is_ok_and(|metadata| metadata.file_type().is_symlink()) {
+            return Err(MpkgError::UnsafePath {
+                name: path.display().to_string(),
+                offset: entry.name_offset,
+            });
+        }
+    }
+    Ok(())
+}
+
+/// 创建输出文件及其所在文件夹
+fn create_output_file(path: &Path) -> io::Result<File> {
+    if let Some(parent_dir) = path.parent() {
+        fs::create_dir_all(parent_dir)?;
+    }
+    File::create(path)
+}
+
+/// 解包单个MPKG文件，返回格式版本、解包与跳过的文件数量以及输出文件夹
+pub fn unpack_mpkg<P: AsRef<Path>>(
+    input_file: P,
+    output_dir: P,
+) -> Result<UnpackReport, MpkgError> {
+    unpack_mpkg_with_progress(input_file, output_dir, print_progress(LogLevel::default()))
+}
+
+/// 解包单个MPKG文件，并通过 `on_progress` 报告进度
+pub fn unpack_mpkg_with_progress<P, F>(
+    input_file: P,
+    output_dir: P,
+    on_progress: F,
+) -> Result<UnpackReport, MpkgError>
+where
+    P: AsRef<Path>,
+    F: FnMut(ProgressEvent),
+{
+    unpack_with_options_and_progress(
+        input_file,
+        output_dir,
+        &UnpackOptions::default(),
+        on_progress,
+    )
+}
+
+/// 解包单个MPKG文件到 `output_dir` 下名为 `subfolder_name` 的文件夹，不使用MPKG文件名
+///
+/// 文件夹名按原样使用，不是单个普通文件夹名时返回 `MpkgError::BadFolderName`。
+pub fn unpack_mpkg_named<P: AsRef<Path>>(
+    input_file: P,
+    output_dir: P,
+    subfolder_name: &str,
+) -> Result<UnpackReport, MpkgError> {
+    let folder = check_folder_name(OsString::from(subfolder_name))?;
+    let options = UnpackOptions {
+        per_archive_folder: false,
+        ..UnpackOptions::default()
+    };
+    unpack_file(
+        input_file.as_ref(),
+        &output_dir.as_ref().join(folder),
+        &options,
+        print_progress(options.log_level),
+    )
+}
+
+/// 按给定选项解包单个MPKG文件
+pub fn unpack_with_options<P: AsRef<Path>>(
+    input_file: P,
+    output_dir: P,
+    options: &UnpackOptions,
+) -> Result<(), MpkgError> {
+    unpack_with_options_and_progress(
+        input_file,
+        output_dir,
+        options,
+        print_progress(options.log_level),
+    )?;
+    Ok(())
+}
+
+/// 按给定选项解包单个MPKG文件，并通过 `on_progress` 报告进度，返回解包统计
+pub fn unpack_with_options_and_progress<P, F>(
+    input_file: P,
+    output_dir: P,
+    options: &UnpackOptions,
+    on_progress: F,
+) -> Result<UnpackReport, MpkgError>
+where
+    P: AsRef<Path>,
+    F: FnMut(ProgressEvent),
+{
+    unpack_file(
+        input_file.as_ref(),
+        output_dir.as_ref(),
+        options,
+        on_progress,
+    )
+}
+
+/// 按给定选项解包单个MPKG文件并打印进度，返回解包、跳过与失败的文件统计
+pub fn unpack_with_report<P: AsRef<Path>>(
+    input_file: P,
+    output_dir: P,
+    options: &UnpackOptions,
+) -> Result<UnpackReport, MpkgError> {
+    unpack_file(
+        input_file.as_ref(),
+        output_dir.as_ref(),
+        options,
+        print_progress(options.log_level),
+    )
+}
+
+/// 由MPKG文件名得到输出文件夹名
+///
+/// 优先使用去掉扩展名的文件名；没有主干时退回完整文件名，仍为空时报错。
+fn archive_folder_name(input_file: &Path) -> Result<&OsStr, MpkgError> {
+    input_file
+        .file_stem()
+        .filter(|stem| !stem.is_empty())
+        .or_else(|| input_file.file_name())
+        .filter(|name| !name.is_empty())
+        .ok_or_else(|| MpkgError::BadFolderName(input_file.display().to_string()))
+}
+
+/// 按 `subfolder_template` 得到输出文件夹名，替换其中的 `{stem}`、`{name}` 与 `{version}`
+///
+/// 模板包含未知的占位符时返回 `MpkgError::InvalidOptions`，
+/// 替换结果为空或不是单个普通文件夹名时返回 `MpkgError::BadFolderName`。
+pub(crate) fn expand_folder_template(
+    template: &str,
+    input_file: &Path,
+    version: &str,
+) -> Result<OsString, MpkgError> {
+    let stem = archive_folder_name(input_file)?;
+    let placeholders = [
+        ("{stem}", stem),
+        ("{name}", input_file.file_name().unwrap_or(stem)),
+        ("{version}", OsStr::new(version)),
+    ];
+
+    let mut folder = OsString::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        folder.push(&rest[..start]);
+        rest = &rest[start..];
+        let (placeholder, value) = placeholders
+            .iter()
+            .find(|(placeholder, _)| rest.starts_with(placeholder))
+            .ok_or_else(|| {
+                MpkgError::InvalidOptions(format!("子文件夹模板中有未知的占位符：{}", rest))
+            })?;
+        folder.push(value);
+        rest = &rest[placeholder.len()..];
+    }
+    folder.push(rest);
+    check_folder_name(folder)
+}
+
+/// 检查输出文件夹名是否为单个普通文件夹名，不为空、不含路径分隔符，也不是 `.` 或 `..`
+fn check_folder_name(folder: OsString) -> Result<OsString, MpkgError> {
+    let has_separator = folder.to_string_lossy().contains(['/', '\\']);
+    let mut components = Path::new(&folder).components();
+    match (components.next(), components.next()) {
+        (Some(Component::Normal(_)), None) if !has_separator => Ok(folder),
+        _ => Err(MpkgError::BadFolderName(
+            folder.to_string_lossy().into_owned(),
+        )),
+    }
+}
+
+/// 解包单个MPKG文件到 `output_dir` 下以文件名（或 `subfolder_template`）命名的文件夹
+///
+/// 关闭 `per_archive_folder` 时直接写入 `output_dir`。
+///
+/// 启用 `compression` 特性时，以gzip魔数开头的文件会先解压再顺序解包，见 `unpack_gzip_file`。
+fn unpack_file<F>(
+    input_file: &Path,
+    output_dir: &Path,
+    options: &UnpackOptions,
+    on_progress: F,
+) -> Result<UnpackReport, MpkgError>
+where
+    F: FnMut(ProgressEvent),
+{
+    #[cfg(feature = "compression")]
+    if is_gzip(input_file)? {
+        return unpack_gzip_file(input_file, output_dir, options, on_progress);
+    }
+
+    let mut input_stream = SeekBufReader::new(File::open(input_file)?);
+    let unpacked_folder = output_folder(input_file, output_dir, options, || {
+        // 读取头部后回到文件开头
+        let archive_len = input_stream.get_ref().metadata()?.len();
+        let version = read_header(&mut input_stream, Some(archive_len), options)?;
+        input_stream.rewind()?;
+        Ok(version)
+    })?;
+    unpack_stream(
+        &mut input_stream,
+        StreamSource::File(input_file),
+        &unpacked_folder,
+        options,
+        on_progress,
+    )
+}
+
+/// 计算 `input_file` 的输出文件夹：`output_dir` 下以文件名（或 `subfolder_template`）命名的文件夹
+///
+/// 只有模板用到 `{version}` 时才调用 `read_version` 读取头部。
+fn output_folder<F>(
+    input_file: &Path,
+    output_dir: &Path,
+    options: &UnpackOptions,
+    read_version: F,
+) -> Result<PathBuf, MpkgError>
+where
+    F: FnOnce() -> Result<String, MpkgError>,
+{
+    match &options.subfolder_template {
+        _ if !options.per_archive_folder => Ok(output_dir.to_path_buf()),
+        None => Ok(output_dir.join(archive_folder_name(input_file)?)),
+        Some(template) => {
+            let version = if template.contains("{version}") {
+                read_version()?
+            } else {
+                String::new()
+            };
+            Ok(output_dir.join(expand_folder_template(template, input_file, &version)?))
+        }
+    }
+}
+
+/// 从已打开的流中解包MPKG文件，输出到 `output_dir` 下名为 `name_hint` 的文件夹
+///
+/// 流会被逐段读取，建议传入带缓冲的读取器（如 `BufReader` 或 `Cursor`）。
+pub fn unpack_mpkg_from_reader<R: Read + Seek, P: AsRef<Path>>(
+    mut reader: R,
+    output_dir: P,
+    name_hint: &str,
+) -> Result<(), MpkgError> {
+    let unpacked_folder = output_dir.as_ref().join(name_hint);
+    unpack_stream(
+        &mut reader,
+        StreamSource::Reader,
+        &unpacked_folder,
+        &UnpackOptions::default(),
+        print_progress(LogLevel::default()),
+    )?;
+    Ok(())
+}
+
+/// 从内存中的整个MPKG文件解包，输出到 `output_dir` 下名为 `name_hint` 的文件夹
+pub fn unpack_mpkg_from_bytes<P: AsRef<Path>>(
+    data: &[u8],
+    output_dir: P,
+    name_hint: &str,
+) -> Result<(), MpkgError> {
+    unpack_mpkg_from_reader(Cursor::new(data), output_dir, name_hint)
+}
+
+/// 只解包文件名匹配通配符 `filter` 的文件，返回解包与跳过的文件数量
+///
+/// 不匹配的文件直接按大小跳过其数据，不会被读取。`filter` 为 `None` 时解包全部文件。
+pub fn unpack_mpkg_filtered<P: AsRef<Path>>(
+    input_file: P,
+    output_dir: P,
+    filter: Option<&str>,
+) -> Result<UnpackReport, MpkgError> {
+    let options = UnpackOptions {
+        filter: filter.map(str::to_string),
+        ..UnpackOptions::default()
+    };
+    unpack_file(
+        input_file.as_ref(),
+        output_dir.as_ref(),
+        &options,
+        print_progress(options.log_level),
+    )
+}
+
+/// 输出文件的写入缓冲区大小，大量小文件时合并写入，缓冲区不超过文件本身的大小
+fn output_capacity(entry: &MpkgEntry, options: &UnpackOptions) -> usize {
+    options
+        .buffer_size
+        .min(usize::try_from(entry.size).unwrap_or(usize::MAX))
+}
+
+/// 将一个文件的数据写入 `output` 并检查写入的长度，`input` 应位于该文件数据的起始处
+///
+/// 启用 `verify` 时同时校验CRC32。返回写入的字节数。
+fn write_entry_data<R, W, F>(
+    input: &mut R,
+    output: &mut W,
+    entry: &MpkgEntry,
+    options: &UnpackOptions,
+    mut on_copied: F,
+) -> Result<u64, MpkgError>
+where
+    R: Read + Seek,
+    W: Write,
+    F: FnMut(u64),
+{
+    let written = if options.verify {
+        // 未知字段保存的是文件内容的CRC32
+        let mut checked_stream = Crc32Writer::new(output);
+        let written = copy_stream_data(
+            input,
+            &mut checked_stream,
+            entry.offset,
+            entry.size,
+            options.buffer_size,
+            options.cancel.as_deref(),
+            &mut on_copied,
+        )?;
+        if checked_stream.crc() != entry.extra {
+            return Err(MpkgError::ChecksumMismatch {
+                name: entry.name.clone(),
+                expected: entry.extra,
+                got: checked_stream.crc(),
+                offset: entry.offset,
+            });
+        }
+        written
+    } else {
+        copy_entry_data(input, output, entry, options, &mut on_copied)?
+    };
+
+    // 原样复制的数据必须与文件表记录的大小一致，解压后的长度已在解压时检查
+    if !options.inflates(entry) && written != entry.size {
+        return Err(MpkgError::DataTruncated {
+            expected: entry.size,
+            got: written,
+            offset: entry.offset + written,
+        });
+    }
+    Ok(written)
+}
+
+/// 创建输出文件并写入一个文件的数据，`input` 应位于该文件数据的起始处，返回写入的字节数
+fn write_entry<R, F>(
+    input: &mut R,
+    target: &Path,
+    entry: &MpkgEntry,
+    options: &UnpackOptions,
+    on_copied: F,
+) -> Result<u64, MpkgError>
+where
+    R: Read + Seek,
+    F: FnMut(u64),
+{
+    let output_stream =
+        BufWriter::with_capacity(output_capacity(entry, options), create_output_file(target)?);
+    let result =
+        finish_entry(input, output_stream, entry, options, on_copied).map_err(|e| match e {
+            MpkgError::Io(e) if is_out_of_space(&e) => MpkgError::WriteFailed {
+                path: target.to_path_buf(),
+                source: e,
+            },
+            e => e,
+        });
+    if result.is_err() && options.remove_partial {
+        // 保留原来的错误，删除失败不影响返回值
+        let _ = fs::remove_file(target);
+    }
+    result
+}
+
+/// 将数据写入已创建的输出文件并应用修改时间
+fn finish_entry<R, F>(
+    input: &mut R,
+    mut output_stream: BufWriter<File>,
+    entry: &MpkgEntry,
+    options: &UnpackOptions,
+    on_copied: F,
+) -> Result<u64, MpkgError>
+where
+    R: Read + Seek,
+    F: FnMut(u64),
+{
+    let written = write_entry_data(input, &mut output_stream, entry, options, on_copied)?;
+
+    // 写出缓冲区中剩余的数据，之后才能设置修改时间
+    let output_file = output_stream.into_inner().map_err(|e| e.into_error())?;
+
+    // 包内记录了修改时间时应用到输出文件
+    if let (true, Some(modified)) = (options.preserve_timestamps, entry.modified) {
+        output_file.set_modified(UNIX_EPOCH + Duration::from_secs(modified))?;
+    }
+    apply_default_mode(&output_file, options.default_mode)?;
+    Ok(written)
+}
+
+/// 在Unix上通过已打开的文件设置权限位，只读权限也不影响之前的写入
+#[cfg(unix)]
+pub(crate) fn apply_default_mode(file: &File, mode: Option<u32>) -> io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    match mode {
+        Some(mode) => file.set_permissions(fs::Permissions::from_mode(mode)),
+        None => Ok(()),
+    }
+}
+
+/// 其他平台没有Unix权限位，忽略 `default_mode`
+#[cfg(not(unix))]
+pub(crate) fn apply_default_mode(_file: &File, _mode: Option<u32>) -> io::Result<()> {
+    Ok(())
+}
+
+/// 写入错误是否表示磁盘已满或输出无法继续写入
+fn is_out_of_space(error: &io::Error) -> bool {
+    matches!(
+        error.kind(),
+        io::ErrorKind::WriteZero | io::ErrorKind::StorageFull | io::ErrorKind::QuotaExceeded
+    )
+}
+
+/// 从流中解包MPKG文件到 `unpacked_folder`
+///
+/// 启用 `atomic` 时先解包到同级的 `.partial` 文件夹，全部成功后再移动到最终位置，
+/// 解包失败时删除临时文件夹；移动失败时保留临时文件夹，原有的输出文件夹保持不变。
+/// 最终文件夹已存在时只有覆盖策略为 `Overwrite` 才会替换它。
+/// 同时启用 `resume` 时会沿用上次留下的临时文件夹，失败时也保留它以便再次继续。
+/// `source` 为流对应的MPKG文件，给出时较大的包会按 `threads` 并行解包。
+fn unpack_stream<R, F>(
+    input_stream: &mut R,
+    source: StreamSource<'_>,
+    unpacked_folder: &Path,
+    options: &UnpackOptions,
+    on_progress: F,
+) -> Result<UnpackReport, MpkgError>
+where
+    R: Read + Seek,
+    F: FnMut(ProgressEvent),
+{
+    if !options.atomic {
+        return unpack_into(input_stream, source, unpacked_folder, options, on_progress);
+    }
+    if options.overwrite != OverwritePolicy::Overwrite && unpacked_folder.exists() {
+        return Err(MpkgError::OutputExists(unpacked_folder.to_path_buf()));
+    }
+
+    // 清理上次中断时留下的临时文件夹
+    let partial = partial_folder(unpacked_folder);
+    if partial.exists() && !options.resume {
+        fs::remove_dir_all(&partial)?;
+    }
+    let mut report = match unpack_into(input_stream, source, &partial, options, on_progress) {
+        Ok(report) => report,
+        Err(e) => {
+            if !options.resume {
+                let _ = fs::remove_dir_all(&partial);
+            }
+            return Err(e);
+        }
+    };
+    // 空包且不创建文件夹时没有需要移动的临时文件夹
+    if partial.exists() {
+        commit_folder(&partial, unpacked_folder)?;
+    }
+    report.output_dir = unpacked_folder.to_path_buf();
+    Ok(report)
+}
+
+/// 从流中解包MPKG文件，直接写入 `unpacked_folder`
+fn unpack_into<R, F>(
+    input_stream: &mut R,
+    source: StreamSource<'_>,
+    unpacked_folder: &Path,
+    options: &UnpackOptions,
+    mut on_progress: F,
+) -> Result<UnpackReport, MpkgError>
+where
+    R: Read + Seek,
+    F: FnMut(ProgressEvent),
+{
+    options.validate()?;
+
+    // 读取头部信息并构建文件列表
+    let table = match source {
+        #[cfg(feature = "compression")]
+        StreamSource::Sequential => read_leading_table(input_stream, options)?,
+        _ => read_archive_table(input_stream, options)?,
+    };
+    let archive = prepare_table(table, unpacked_folder, options)?;
+
+    // 头部与文件表都有效后再创建输出文件夹，空包可以选择不创建
+    let create_folder = options.create_empty_folder || !archive.entries.is_empty();
+    if create_folder {
+        fs::create_dir_all(unpacked_folder)?;
+    }
+    let total_bytes = archive
+        .entries
+        .iter()
+        .zip(&archive.targets)
+        .filter(|(_, target)| target.is_some())
+        .map(|(entry, _)| entry.size)
+        .sum();
+    on_progress(ProgressEvent::ArchiveOpened {
+        version: archive.version.clone(),
+        file_count: archive.entries.len(),
+        total_bytes,
+    });
+
+    // 逐个解包文件到指定文件夹
+    let mut report = UnpackReport {
+        version: archive.version.clone(),
+        output_dir: unpacked_folder.to_path_buf(),
+        ..UnpackReport::default()
+    };
+    let threads = parallel_threads(options, &archive.targets, total_bytes);
+    let extracted = match source {
+        StreamSource::File(source) if threads > 1 => unpack_entries_parallel(
+            source,
+            unpacked_folder,
+            &archive,
+            options,
+            threads,
+            &mut report,
+            &mut on_progress,
+        )?,
+        _ => unpack_entries(
+            input_stream,
+            unpacked_folder,
+            &archive,
+            options,
+            &mut report,
+            &mut on_progress,
+        )?,
+    };
+
+    if options.manifest && create_folder {
+        write_manifest(
+            unpacked_folder,
+            &archive.version,
+            &archive.entries,
+            &extracted,
+        )?;
+    }
+    if options.index_file && create_folder {
+        write_index(unpacked_folder, &archive.entries)?;
+    }
+
+    on_progress(ProgressEvent::Finished);
+    Ok(report)
+}
+
+/// 按覆盖策略确定文件实际写入的路径，`None` 表示跳过该文件
+///
+/// 增量更新时只重写CRC32不符的文件，继续解包时只重写大小不符的文件；
+/// 启用 `refuse_symlinks` 时检查路径中没有符号链接。
+fn output_target<'a>(
+    unpacked_folder: &Path,
+    entry: &MpkgEntry,
+    target: Option<&'a PathBuf>,
+    options: &UnpackOptions,
+) -> Result<Option<&'a PathBuf>, MpkgError> {
+    let target = match target {
+        Some(target) if options.skip_unchanged => {
+            (!unchanged(target, entry, options.buffer_size)).then_some(target)
+        }
+        Some(target) if options.resume => {
+            (!already_extracted(target, entry.size)).then_some(target)
+        }
+        Some(target) if should_write(target, options.overwrite)? => Some(target),
+        _ => None,
+    };
+    if let (true, Some(target)) = (options.refuse_symlinks, target) {
+        check_no_symlinks(unpacked_folder, target, entry)?;
+    }
+    Ok(target)
+}
+
+/// 把被跳过的文件计入报告，被 `exclude` 排除的文件单独计数
+fn record_skipped(report: &mut UnpackReport, entry: &MpkgEntry, options: &UnpackOptions) {
+    if is_excluded(entry, options) {
+        report.files_excluded += 1;
+    } else {
+        report.files_skipped += 1;
+    }
+}
+
+/// 按文件表顺序逐个解包文件，返回与文件表一一对应的、各文件是否实际写入的标记
+fn unpack_entries<R, F>(
+    input_stream: &mut R,
+    unpacked_folder: &Path,
+    archive: &PreparedArchive,
+    options: &UnpackOptions,
+    report: &mut UnpackReport,
+    on_progress: &mut F,
+) -> Result<Vec<bool>, MpkgError>
+where
+    R: Read + Seek,
+    F: FnMut(ProgressEvent),
+{
+    let mut position = input_stream.stream_position()?;
+    let mut copied_before = 0;
+    let mut extracted = vec![false; archive.entries.len()];
+    for (i, (entry, target)) in archive.entries.iter().zip(&archive.targets).enumerate() {
+        check_cancelled(options.cancel.as_deref())?;
+
+        let Some(target) = output_target(unpacked_folder, entry, target.as_ref(), options)? else {
+            record_skipped(report, entry, options);
+            on_progress(ProgressEvent::FileSkipped {
+                name: entry.name.clone(),
+                index: i,
+            });
+            continue;
+        };
+
+        // 跳过的文件不读取其数据，直接定位到当前文件的数据
+        if position != entry.offset {
+            input_stream.seek(SeekFrom::Start(entry.offset))?;
+        }
+
+        on_progress(ProgressEvent::FileStarted {
+            name: entry.name.clone(),
+            index: i,
+            total: archive.entries.len(),
+            size: entry.size,
+            offset: entry.offset,
+        });
+
+        // 复制数据
+        let result = write_entry(input_stream, target, entry, options, |file_bytes| {
+            on_progress(ProgressEvent::BytesCopied {
+                file_bytes,
+                total_bytes: copied_before + file_bytes,
+            })
+        });
+        copied_before += entry.size;
+        position = entry.offset + entry.size;
+        let written = match result {
+            Ok(written) => written,
+            Err(e) if !options.continue_on_error || matches!(e, MpkgError::Cancelled) => {
+                return Err(e);
+            }
+            Err(e) => {
+                // 跳过出错文件的剩余数据，继续解包下一个文件
+                on_progress(ProgressEvent::FileFailed {
+                    name: entry.name.clone(),
+                    index: i,
+                    error: e.to_string(),
+                });
+                report.files_failed.push(entry.name.clone());
+                input_stream.seek(SeekFrom::Start(position))?;
+                continue;
+            }
+        };
+        extracted[i] = true;
+        report.files_extracted += 1;
+        report.bytes_written += written;
+        on_progress(ProgressEvent::FileFinished {
+            name: entry.name.clone(),
+        });
+    }
+
+    Ok(extracted)
+}
+
+/// 包内要解包的文件少于该数量且数据少于 `PARALLEL_MIN_BYTES` 时逐个解包，省去创建线程的开销
+const PARALLEL_MIN_FILES: usize = 64;
+
+/// 包内要解包的数据少于该字节数且文件少于 `PARALLEL_MIN_FILES` 时逐个解包
+const PARALLEL_MIN_BYTES: u64 = 32 * 1024 * 1024;
+
+/// 根据要解包的文件数量与数据量确定解包使用的线程数，1 表示逐个解包
+fn parallel_threads(
+    options: &UnpackOptions,
+    targets: &[Option<PathBuf>],
+    total_bytes: u64,
+) -> usize {
+    let selected = targets.iter().filter(|target| target.is_some()).count();
+    if selected < 2 || (selected < PARALLEL_MIN_FILES && total_bytes < PARALLEL_MIN_BYTES) {
+        return 1;
+    }
+    options.thread_count().min(selected)
+}
+
+/// 并行解包时等待调用线程处理的消息数上限
+const PROGRESS_BACKLOG: usize = 256;
+
+/// 并行解包时工作线程发给调用线程的消息
+enum WorkerMessage {
+    Progress(ProgressEvent),
+    /// 一个文件解包完成，附带其在文件表中的序号与写入的字节数
+    Extracted {
+        index: usize,
+        written: u64,
+    },
+    /// 启用 `continue_on_error` 时一个文件解包失败
+    Failed(String),
+}
+
+/// 使用 `threads` 个线程并行解包文件，每个线程各自打开 `source` 并按偏移读取分配到的文件
+///
+/// 覆盖策略在调用线程中预先判断；进度事件经通道交回调用线程报告，
+/// 因此不同文件的事件可能交错，`BytesCopied` 的 `total_bytes` 为所有线程的合计。
+/// 出错后其余线程不再领取新文件，返回最先出现的错误；成功时返回值与 `unpack_entries` 相同。
+fn unpack_entries_parallel<F>(
+    source: &Path,
+    unpacked_folder: &Path,
+    archive: &PreparedArchive,
+    options: &UnpackOptions,
+    threads: usize,
+    report: &mut UnpackReport,
+    on_progress: &mut F,
+) -> Result<Vec<bool>, MpkgError>
+where
+    F: FnMut(ProgressEvent),
+{
+    let mut targets = Vec::with_capacity(archive.entries.len());
+    for (i, (entry, target)) in archive.entries.iter().zip(&archive.targets).enumerate() {
+        let target = output_target(unpacked_folder, entry, target.as_ref(), options)?;
+        if target.is_none() {
+            record_skipped(report, entry, options);
+            on_progress(ProgressEvent::FileSkipped {
+                name: entry.name.clone(),
+                index: i,
+            });
+        }
+        targets.push(target);
+    }
+
+    // 各线程从共享的计数器领取下一个待解包的文件
+    let next_index = AtomicUsize::new(0);
+    let copied_total = AtomicU64::new(0);
+    let first_error = Mutex::new(None);
+    let unpack_assigned = |sender: &mpsc::SyncSender<WorkerMessage>| -> Result<(), MpkgError> {
+        let mut archive_file = File::open(source)?;
+        loop {
+            let i = next_index.fetch_add(1, Ordering::Relaxed);
+            let (Some(entry), Some(target)) = (archive.entries.get(i), targets.get(i)) else {
+                return Ok(());
+            };
+            let Some(target) = target else {
+                continue;
+            };
+            check_cancelled(options.cancel.as_deref())?;
+            let _ = sender.send(WorkerMessage::Progress(ProgressEvent::FileStarted {
+                name: entry.name.clone(),
+                index: i,
+                total: archive.entries.len(),
+                size: entry.size,
+                offset: entry.offset,
+            }));
+
+            archive_file.seek(SeekFrom::Start(entry.offset))?;
+            let mut reported = 0;
+            let result = write_entry(&mut archive_file, target, entry, options, |file_bytes| {
+                let added = file_bytes - reported;
+                reported = file_bytes;
+                let total_bytes = copied_total.fetch_add(added, Ordering::Relaxed) + added;
+                let _ = sender.send(WorkerMessage::Progress(ProgressEvent::BytesCopied {
+                    file_bytes,
+                    total_bytes,
+                }));
+            });
+            match result {
+                Ok(written) => {
+                    let _ = sender.send(WorkerMessage::Extracted { index: i, written });
+                    let _ = sender.send(WorkerMessage::Progress(ProgressEvent::FileFinished {
+                        name: entry.name.clone(),
+                    }));
+                }
+                Err(e) if !options.continue_on_error || matches!(e, MpkgError::Cancelled) => {
+                    return Err(e);
+                }
+                Err(e) => {
+                    let _ = sender.send(WorkerMessage::Progress(ProgressEvent::FileFailed {
+                        name: entry.name.clone(),
+                        index: i,
+                        error: e.to_string(),
+                    }));
+                    let _ = sender.send(WorkerMessage::Failed(entry.name.clone()));
+                }
+            }
+        }
+    };
+
+    let worker = &|sender: mpsc::SyncSender<WorkerMessage>| {
+        if let Err(e) = unpack_assigned(&sender) {
+            // 出错后让其余线程尽快停止领取新文件
+            next_index.store(archive.entries.len(), Ordering::Relaxed);
+            first_error.lock().unwrap().get_or_insert(e);
+        }
+    };
+
+    // 通道有界，避免工作线程远远领先于进度报告，取消时也能尽快停下
+    let (sender, receiver) = mpsc::sync_channel(PROGRESS_BACKLOG);
+    let mut extracted = vec![false; archive.entries.len()];
+    thread::scope(|scope| {
+        for _ in 0..threads {
+            let sender = sender.clone();
+            scope.spawn(move || worker(sender));
+        }
+        // 所有线程结束、发送端全部释放后接收才会结束
+        drop(sender);
+        for message in receiver {
+            match message {
+                WorkerMessage::Progress(event) => on_progress(event),
+                WorkerMessage::Extracted { index, written } => {
+                    extracted[index] = true;
+                    report.files_extracted += 1;
+                    report.bytes_written += written;
+                }
+                WorkerMessage::Failed(name) => report.files_failed.push(name),
+            }
+        }
+    });
+
+    match first_error.into_inner().unwrap() {
+        Some(e) => Err(e),
+        None => Ok(extracted),
+    }
+}
+
+/// 使用 `threads` 个线程并行解包单个MPKG文件
+///
+/// 文件表只解析一次，之后每个线程各自打开一个文件句柄，
+/// 按偏移定位并写出分配到的文件。此函数不打印任何进度。
+/// 与设置了 `UnpackOptions::threads` 的 `unpack_with_options` 相同，较小的包仍逐个解包。
+pub fn unpack_mpkg_parallel<P: AsRef<Path>>(
+    input_file: P,
+    output_dir: P,
+    threads: usize,
+) -> Result<(), MpkgError> {
+    let options = UnpackOptions {
+        threads: Some(threads.max(1)),
+        ..UnpackOptions::default()
+    };
+    unpack_file(input_file.as_ref(), output_dir.as_ref(), &options, |_| {})?;
+    Ok(())
+}
+
+/// 列出文件夹中扩展名属于 `options.extensions` 的所有MPKG文件
+///
+/// 启用 `recursive` 时同时扫描子文件夹，但不进入指向文件夹的符号链接，以免循环。
+pub fn find_archives<P: AsRef<Path>>(
+    input_dir: P,
+    options: &UnpackOptions,
+) -> Result<Vec<PathBuf>, MpkgError> {
+    let mut archives = Vec::new();
+    collect_archives(input_dir.as_ref(), options, &mut archives)?;
+    Ok(archives)
+}
+
+fn collect_archives(
+    dir: &Path,
+    options: &UnpackOptions,
+    archives: &mut Vec<PathBuf>,
+) -> io::Result<()> {
+    let mut entries = fs::read_dir(dir)?.collect::<io::Result<Vec<_>>>()?;
+    if options.sort_scan {
+        entries.sort_by_key(|entry| entry.path());
+    }
+
+    for entry in entries {
+        let path = entry.path();
+        if options.recursive && entry.file_type()?.is_dir() {
+            collect_archives(&path, options, archives)?;
+        } else if path.is_file() && options.has_archive_extension(&path) {
+            archives.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// 取路径的规范形式；路径尚不存在时规范化其最近的已存在上级，再拼上其余部分
+fn canonicalize_lenient(path: &Path) -> io::Result<PathBuf> {
+    let absolute = std::path::absolute(path)?;
+    for ancestor in absolute.ancestors() {
+        if let Ok(canonical) = ancestor.canonicalize() {
+            let rest = absolute.strip_prefix(ancestor).unwrap_or(Path::new(""));
+            return Ok(canonical.join(rest));
+        }
+    }
+    Ok(absolute)
+}
+
+/// 判断输出文件夹是否与输入文件夹相同或位于其中，两者都先规范化再比较
+///
+/// 输出文件夹不必已经存在。
+pub fn output_within_input<P: AsRef<Path>>(input_dir: P, output_dir: P) -> Result<bool, MpkgError> {
+    let input_dir = input_dir.as_ref().canonicalize()?;
+    let output_dir = canonicalize_lenient(output_dir.as_ref())?;
+    Ok(output_dir.starts_with(input_dir))
+}
+
+/// 递归扫描时输出文件夹不能位于输入文件夹之内，否则解包出的文件会在下次扫描时被再次处理
+fn check_scan_output(
+    input_dir: &Path,
+    output_dir: &Path,
+    options: &UnpackOptions,
+) -> Result<(), MpkgError> {
+    if options.recursive && output_within_input(input_dir, output_dir)? {
+        return Err(MpkgError::InvalidOptions(format!(
+            "递归扫描时输出文件夹 {} 不能位于输入文件夹 {} 之内",
+            output_dir.display(),
+            input_dir.display()
+        )));
+    }
+    Ok(())
+}
+
+/// 子文件夹中的MPKG文件解包到输出文件夹下对应的相对位置
+fn archive_output_dir(input_dir: &Path, output_dir: &Path, archive: &Path) -> PathBuf {
+    let relative_dir = archive
+        .parent()
+        .and_then(|parent| parent.strip_prefix(input_dir).ok())
+        .unwrap_or(Path::new(""));
+    output_dir.join(relative_dir)
+}
+
+/// 文件夹中单个MPKG文件的路径及其解包结果
+pub type ArchiveResult = (PathBuf, Result<(), MpkgError>);
+
+/// 解包文件夹中的所有MPKG文件，返回每个文件的解包结果
+///
+/// 单个文件失败不影响其余文件；只有读取文件夹本身失败时才返回错误。
+pub fn unpack_dir<P: AsRef<Path>>(
+    input_dir: P,
+    output_dir: P,
+) -> Result<Vec<ArchiveResult>, MpkgError> {
+    unpack_dir_with_options(input_dir, output_dir, &UnpackOptions::default())
+}
+
+/// 按给定选项解包文件夹中的所有MPKG文件，返回每个文件的解包结果
+///
+/// 启用 `recursive` 时输出文件夹位于输入文件夹之内会返回 `MpkgError::InvalidOptions`。
+pub fn unpack_dir_with_options<P: AsRef<Path>>(
+    input_dir: P,
+    output_dir: P,
+    options: &UnpackOptions,
+) -> Result<Vec<ArchiveResult>, MpkgError> {
+    unpack_dir_with_progress(
+        input_dir,
+        output_dir,
+        options,
+        print_progress(options.log_level),
+    )
+}
+
+/// 按给定选项解包文件夹中的所有MPKG文件，并通过 `on_progress` 依次报告每个文件的进度
+pub fn unpack_dir_with_progress<P, F>(
+    input_dir: P,
+    output_dir: P,
+    options: &UnpackOptions,
+    on_progress: F,
+) -> Result<Vec<ArchiveResult>, MpkgError>
+where
+    P: AsRef<Path>,
+    F: FnMut(ProgressEvent),
+{
+    unpack_dir_with_archive_progress(input_dir, output_dir, options, |_| {}, on_progress)
+}
+
+/// 解包文件夹中的所有MPKG文件，`on_archive` 报告每个包的开始与结束，`on_progress` 报告包内的进度
+pub fn unpack_dir_with_archive_progress<P, A, F>(
+    input_dir: P,
+    output_dir: P,
+    options: &UnpackOptions,
+    mut on_archive: A,
+    mut on_progress: F,
+) -> Result<Vec<ArchiveResult>, MpkgError>
+where
+    P: AsRef<Path>,
+    A: FnMut(DirProgressEvent),
+    F: FnMut(ProgressEvent),
+{
+    let input_dir = input_dir.as_ref();
+    let output_dir = output_dir.as_ref();
+    check_scan_output(input_dir, output_dir, options)?;
+    let archives = find_archives(input_dir, options)?;
+    let total = archives.len();
+    let results = archives
+        .into_iter()
+        .enumerate()
+        .map(|(index, path)| {
+            on_archive(DirProgressEvent::ArchiveStarted {
+                path: path.clone(),
+                index,
+                total,
+            });
+            let archive_output = archive_output_dir(input_dir, output_dir, &path);
+            let result = unpack_with_options_and_progress(
+                path.as_path(),
+                archive_output.as_path(),
+                options,
+                &mut on_progress,
+            );
+            on_archive(DirProgressEvent::ArchiveFinished {
+                path: path.clone(),
+                index,
+                result: result
+                    .as_ref()
+                    .map(Clone::clone)
+                    .map_err(ToString::to_string),
+            });
+            (path, result.map(|_| ()))
+        })
+        .collect();
+    Ok(results)
+}
+
+/// 解包文件夹中的所有MPKG文件，单个文件失败不影响其余文件
+pub fn unpack_all_in_dir<P: AsRef<Path>>(input_dir: P, output_dir: P) -> Result<(), MpkgError> {
+    unpack_all_in_dir_with_options(input_dir, output_dir, &UnpackOptions::default())
+}
+
+/// 按给定选项解包文件夹中的所有MPKG文件，单个文件失败不影响其余文件
+pub fn unpack_all_in_dir_with_options<P: AsRef<Path>>(
+    input_dir: P,
+    output_dir: P,
+    options: &UnpackOptions,
+) -> Result<(), MpkgError> {
+    let input_dir = input_dir.as_ref();
+    let output_dir = output_dir.as_ref();
+    check_scan_output(input_dir, output_dir, options)?;
+
+    let show_progress = options.log_level > LogLevel::Quiet;
+    for path in find_archives(input_dir, options)? {
+        if show_progress {
+            println!(
+                "正在处理文件: {}",
+                path.file_name().unwrap_or_default().to_string_lossy()
+            );
+        }
+        let archive_output = archive_output_dir(input_dir, output_dir, &path);
+        match unpack_with_options(path.as_path(), archive_output.as_path(), options) {
+            Ok(()) if show_progress => println!("成功解包: {}", path.display()),
+            Ok(()) => {}
+            Err(e) => eprintln!("解包失败: {}: {}", path.display(), e),
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::alloc::{GlobalAlloc, Layout as AllocLayout, System};
+    use std::cell::Cell;
+
+    use super::*;
+    use crate::format::Layout;
+
+    /// 记录当前线程最大一次内存分配的分配器，用于确认不可信的长度不会导致预先分配
+    struct TrackingAllocator;
+
+    thread_local! {
+        static LARGEST_ALLOCATION: Cell<usize> = const { Cell::new(0) };
+    }
+
+    unsafe impl GlobalAlloc for TrackingAllocator {
+        unsafe fn alloc(&self, layout: AllocLayout) -> *mut u8 {
+            let _ = LARGEST_ALLOCATION
+                .try_with(|largest| largest.set(largest.get().max(layout.size())));
+            System.alloc(layout)
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: AllocLayout) {
+            System.dealloc(ptr, layout)
+        }
+
+        unsafe fn realloc(&self, ptr: *mut u8, layout: AllocLayout, new_size: usize) -> *mut u8 {
+            let _ = LARGEST_ALLOCATION.try_with(|largest| largest.set(largest.get().max(new_size)));
+            System.realloc(ptr, layout, new_size)
+        }
+    }
+
+    #[global_allocator]
+    static ALLOCATOR: TrackingAllocator = TrackingAllocator;
+
+    /// 运行 `f` 并返回其间当前线程最大一次内存分配的字节数
+    pub(crate) fn largest_allocation<T>(f: impl FnOnce() -> T) -> (T, usize) {
+        LARGEST_ALLOCATION.with(|largest| largest.set(0));
+        let result = f();
+        (result, LARGEST_ALLOCATION.with(Cell::get))
+    }
+
+    /// 在内存中构建MPKG文件，文件表按 `version` 对应的布局写出
+    pub(crate) struct ArchiveBuilder {
+        version: String,
+        entries: Vec<TestEntry>,
+    }
+
+    struct TestEntry {
+        name: Vec<u8>,
+        extra: u32,
+        /// 写入文件表的大小，可以与实际数据长度不同，用于构造损坏的文件
+        size: u64,
+        data: Vec<u8>,
+    }
+
+    impl ArchiveBuilder {
+        pub(crate) fn new(version: &str) -> ArchiveBuilder {
+            ArchiveBuilder {
+                version: version.to_string(),
+                entries: Vec::new(),
+            }
+        }
+
+        /// 追加一个文件
+        pub(crate) fn file(self, name: &str, data: &[u8]) -> ArchiveBuilder {
+            self.entry(name.as_bytes(), 0, data.len() as u64, data)
+        }
+
+        /// 追加一个文件，文件名、未知字段与文件表中的大小都可以任意指定
+        pub(crate) fn entry(
+            mut self,
+            name: &[u8],
+            extra: u32,
+            size: u64,
+            data: &[u8],
+        ) -> ArchiveBuilder {
+            self.entries.push(TestEntry {
+                name: name.to_vec(),
+                extra,
+                size,
+                data: data.to_vec(),
+            });
+            self
+        }
+
+        fn push_table(&self, bytes: &mut Vec<u8>, layout: Layout) {
+            bytes.extend_from_slice(&(self.entries.len() as u32).to_le_bytes());
+            for entry in &self.entries {
+                bytes.extend_from_slice(&(entry.name.len() as u32).to_le_bytes());
+                bytes.extend_from_slice(&entry.name);
+                bytes.extend_from_slice(&entry.extra.to_le_bytes());
+                if layout.wide_sizes {
+                    bytes.extend_from_slice(&entry.size.to_le_bytes());
+                } else {
+                    bytes.extend_from_slice(&(entry.size as u32).to_le_bytes());
+                }
+                if layout.timestamps {
+                    bytes.extend_from_slice(&0u64.to_le_bytes());
+                }
+            }
+        }
+
+        pub(crate) fn build(&self) -> Vec<u8> {
+            let layout = Layout::for_version(&self.version);
+            let mut bytes = Vec::new();
+            bytes.extend_from_slice(&(self.version.len() as u32).to_le_bytes());
+            bytes.extend_from_slice(self.version.as_bytes());
+            if !layout.trailing_index {
+                self.push_table(&mut bytes, layout);
+            }
+            let table_start =
+                bytes.len() + self.entries.iter().map(|e| e.data.len()).sum::<usize>();
+            for entry in &self.entries {
+                bytes.extend_from_slice(&entry.data);
+            }
+            if layout.trailing_index {
+                self.push_table(&mut bytes, layout);
+                bytes.extend_from_slice(&(table_start as u64).to_le_bytes());
+            }
+            bytes
+        }
+    }
+
+    /// 不打印进度的默认选项
+    pub(crate) fn quiet() -> UnpackOptions {
+        UnpackOptions {
+            log_level: LogLevel::Quiet,
+            ..UnpackOptions::default()
+        }
+    }
+
+    /// 将内存中的MPKG文件解包到 `dir` 下的 `out` 文件夹
+    pub(crate) fn unpack_bytes(
+        bytes: &[u8],
+        dir: &Path,
+        options: &UnpackOptions,
+    ) -> Result<UnpackReport, MpkgError> {
+        unpack_stream(
+            &mut Cursor::new(bytes),
+            StreamSource::Reader,
+            &dir.join("out"),
+            options,
+            |_| {},
+        )
+    }
+
+    #[test]
+    fn odd_archive_file_names() {
+        assert_eq!(archive_folder_name(Path::new("a.b.mpkg")).unwrap(), "a.b");
+        assert_eq!(archive_folder_name(Path::new("noext")).unwrap(), "noext");
+        for path in ["/", "..", ""] {
+            assert!(
+                matches!(
+                    archive_folder_name(Path::new(path)),
+                    Err(MpkgError::BadFolderName(_))
+                ),
+                "{:?}",
+                path
+            );
+        }
+
+        // 名为 `.mpkg` 的文件没有扩展名，以完整文件名作为输出文件夹，而不是写入输出根目录
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join(".mpkg");
+        fs::write(
+            &input,
+            ArchiveBuilder::new("PKGV0001").file("a.txt", b"a").build(),
+        )
+        .unwrap();
+        let out = dir.path().join("out");
+        let report = unpack_with_options_and_progress(&input, &out, &quiet(), |_| {}).unwrap();
+        assert_eq!(report.output_dir, out.join(".mpkg"));
+        assert_eq!(fs::read(out.join(".mpkg/a.txt")).unwrap(), b"a");
+        assert!(!out.join("a.txt").exists());
+    }
+
+    #[test]
+    fn mixed_case_extensions() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("in");
+        fs::create_dir(&input).unwrap();
+        let bytes = ArchiveBuilder::new("PKGV0001").file("a.txt", b"a").build();
+        for name in [
+            "Foo.MPKG",
+            "bar.Mpkg",
+            "baz.mpkg",
+            "variant.mpk",
+            "notes.txt",
+        ] {
+            fs::write(input.join(name), &bytes).unwrap();
+        }
+
+        let out = dir.path().join("out");
+        unpack_all_in_dir_with_options(&input, &out, &quiet()).unwrap();
+        let mut folders: Vec<_> = fs::read_dir(&out)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .collect();
+        folders.sort();
+        assert_eq!(folders, ["Foo", "bar", "baz"]);
+
+        // 额外的扩展名同样不区分大小写
+        let options = UnpackOptions {
+            extensions: vec!["mpkg".to_string(), "MPK".to_string()],
+            ..quiet()
+        };
+        let found = find_archives(&input, &options).unwrap();
+        let names: Vec<_> = found.iter().map(|path| path.file_name().unwrap()).collect();
+        assert_eq!(names, ["Foo.MPKG", "bar.Mpkg", "baz.mpkg", "variant.mpk"]);
+    }
+
+    #[test]
+    fn zero_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let bytes = ArchiveBuilder::new("PKGV0001").build();
+        let report = unpack_bytes(&bytes, dir.path(), &quiet()).unwrap();
+        assert_eq!(report.version, "PKGV0001");
+        assert_eq!(report.files_extracted, 0);
+        assert_eq!(fs::read_dir(dir.path().join("out")).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn empty_archive_folder() {
+        let dir = tempfile::tempdir().unwrap();
+        let bytes = ArchiveBuilder::new("PKGV0001").build();
+        let path = dir.path().join("empty.mpkg");
+        fs::write(&path, &bytes).unwrap();
+        let report = validate_mpkg(&path).unwrap();
+        assert_eq!(report.file_count, 0);
+        assert_eq!(report.total_bytes, 0);
+        assert_eq!(report.trailing_bytes, 0);
+
+        // 默认创建空的输出文件夹
+        let report = unpack_bytes(&bytes, dir.path(), &quiet()).unwrap();
+        assert_eq!(report.files_extracted, 0);
+        assert!(dir.path().join("out").is_dir());
+
+        let options = UnpackOptions {
+            create_empty_folder: false,
+            ..quiet()
+        };
+        let other = dir.path().join("other");
+        let report = unpack_bytes(&bytes, &other, &options).unwrap();
+        assert_eq!(report.files_extracted, 0);
+        assert!(!other.join("out").exists());
+
+        // 启用 `atomic` 时同样不留下临时文件夹
+        let options = UnpackOptions {
+            atomic: true,
+            ..options
+        };
+        unpack_bytes(&bytes, &other, &options).unwrap();
+        assert!(!other.exists());
+    }
+
+    #[test]
+    fn duplicate_names() {
+        let bytes = ArchiveBuilder::new("PKGV0001")
+            .file("a.txt", b"first")
+            .file("a.txt", b"second")
+            .build();
+        let unpack = |duplicates| {
+            let dir = tempfile::tempdir().unwrap();
+            let options = UnpackOptions {
+                duplicates,
+                ..quiet()
+            };
+            let result = unpack_bytes(&bytes, dir.path(), &options);
+            (dir, result)
+        };
+
+        let (dir, result) = unpack(DuplicatePolicy::LastWins);
+        let report = result.unwrap();
+        assert_eq!((report.files_extracted, report.files_skipped), (1, 1));
+        assert_eq!(fs::read(dir.path().join("out/a.txt")).unwrap(), b"second");
+
+        let (dir, result) = unpack(DuplicatePolicy::FirstWins);
+        let report = result.unwrap();
+        assert_eq!((report.files_extracted, report.files_skipped), (1, 1));
+        assert_eq!(fs::read(dir.path().join("out/a.txt")).unwrap(), b"first");
+
+        let (dir, result) = unpack(DuplicatePolicy::Rename);
+        assert_eq!(result.unwrap().files_extracted, 2);
+        assert_eq!(fs::read(dir.path().join("out/a.txt")).unwrap(), b"first");
+        assert_eq!(fs::read(dir.path().join("out/a_1.txt")).unwrap(), b"second");
+
+        let (dir, result) = unpack(DuplicatePolicy::Error);
+        match result {
+            Err(MpkgError::DuplicateEntry {
+                name,
+                first_offset,
+                second_offset,
+                ..
+            }) => {
+                assert_eq!(name, "a.txt");
+                assert_eq!(&bytes[first_offset as usize..][..5], b"first");
+                assert_eq!(&bytes[second_offset as usize..][..6], b"second");
+            }
+            other => panic!("{:?}", other),
+        }
+        assert!(!dir.path().join("out").exists());
+    }
+
+    #[test]
+    fn table_order() {
+        // 文件名故意不按字典序排列，确认顺序来自文件表而不是排序
+        let names: Vec<String> = (0..100)
+            .map(|i| format!("f{:03}.bin", (i * 37) % 100))
+            .collect();
+        let bytes = names
+            .iter()
+            .fold(ArchiveBuilder::new("PKGV0001"), |builder, name| {
+                builder.file(name, name.as_bytes())
+            })
+            .build();
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("a.mpkg");
+        fs::write(&input, &bytes).unwrap();
+
+        // 逐个解包时按文件表顺序开始每个文件
+        let mut started = Vec::new();
+        let options = UnpackOptions {
+            threads: Some(1),
+            ..quiet()
+        };
+        unpack_with_options_and_progress(&input, &dir.path().join("serial"), &options, |event| {
+            if let ProgressEvent::FileStarted { index, name, .. } = event {
+                started.push((index, name));
+            }
+        })
+        .unwrap();
+        let expected: Vec<_> = names.iter().cloned().enumerate().collect();
+        assert_eq!(started, expected);
+
+        // 并行解包时清单仍严格按文件表顺序列出，并带有序号
+        let options = UnpackOptions {
+            threads: Some(4),
+            manifest: true,
+            ..quiet()
+        };
+        let out = dir.path().join("parallel");
+        unpack_with_options_and_progress(&input, &out, &options, |_| {}).unwrap();
+        let manifest = fs::read_to_string(out.join("a").join(MANIFEST_FILE_NAME)).unwrap();
+        let mut position = 0;
+        for (index, name) in names.iter().enumerate() {
+            let item = format!("{{\"index\": {}, \"name\": \"{}\"", index, name);
+            let found = manifest[position..]
+                .find(&item)
+                .unwrap_or_else(|| panic!("清单中缺少或顺序错误：{}", item));
+            position += found + item.len();
+        }
+        for name in &names {
+            assert_eq!(fs::read(out.join("a").join(name)).unwrap(), name.as_bytes());
+        }
+    }
+
+    #[test]
+    fn one_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let bytes = ArchiveBuilder::new("PKGV0001")
+            .file("scene.json", b"{}")
+            .build();
+        let report = unpack_bytes(&bytes, dir.path(), &quiet()).unwrap();
+        assert_eq!(report.files_extracted, 1);
+        assert_eq!(report.bytes_written, 2);
+        assert_eq!(fs::read(dir.path().join("out/scene.json")).unwrap(), b"{}");
+    }
+
+    #[test]
+    fn nested_paths() {
+        let dir = tempfile::tempdir().unwrap();
+        for version in ["PKGV0001", "MPKG0002", "MPKG0003", "MPKG0004"] {
+            let bytes = ArchiveBuilder::new(version)
+                .file("a.txt", b"hello")
+                .file("materials/b/c.png", b"png")
+                .file("empty", b"")
+                .build();
+            let out = dir.path().join(version);
+            let report = unpack_bytes(&bytes, &out, &quiet()).unwrap();
+            assert_eq!(report.files_extracted, 3, "{}", version);
+            assert_eq!(fs::read(out.join("out/a.txt")).unwrap(), b"hello");
+            assert_eq!(fs::read(out.join("out/materials/b/c.png")).unwrap(), b"png");
+            assert_eq!(fs::read(out.join("out/empty")).unwrap(), b"");
+        }
+    }
+
+    #[test]
+    fn truncated_data() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut bytes = ArchiveBuilder::new("PKGV0001")
+            .file("a.txt", b"hello")
+            .file("b.txt", b"world")
+            .build();
+        bytes.truncate(bytes.len() - 2);
+        let archive_len = bytes.len() as u64;
+        let result = unpack_bytes(&bytes, dir.path(), &quiet());
+        assert!(
+            matches!(result, Err(MpkgError::DataTruncated { offset, .. }) if offset == archive_len),
+            "{:?}",
+            result
+        );
+        // 文件不完整在写入任何文件之前就被发现
+        assert!(!dir.path().join("out").exists());
+    }
+
+    #[test]
+    fn odd_extra_is_not_compression() {
+        // 未知字段最低位为1的CRC32在默认选项下不能被当作压缩标记
+        let data = (0u8..)
+            .map(|i| vec![b'a', i])
+            .find(|data| {
+                let mut crc = crc32::Crc32::new();
+                crc.update(data);
+                crc.finish() & 1 == 1
+            })
+            .unwrap();
+        let mut crc = crc32::Crc32::new();
+        crc.update(&data);
+        let bytes = ArchiveBuilder::new("PKGV0001")
+            .entry(b"odd.bin", 0x1234_5677, 4, b"abcd")
+            .entry(b"crc.bin", crc.finish(), data.len() as u64, &data)
+            .build();
+        let dir = tempfile::tempdir().unwrap();
+        unpack_bytes(&bytes, dir.path(), &quiet()).unwrap();
+        assert_eq!(fs::read(dir.path().join("out/odd.bin")).unwrap(), b"abcd");
+
+        let options = UnpackOptions {
+            verify: true,
+            ..quiet()
+        };
+        let input = dir.path().join("crc.mpkg");
+        fs::write(
+            &input,
+            ArchiveBuilder::new("PKGV0001")
+                .entry(b"crc.bin", crc.finish(), data.len() as u64, &data)
+                .build(),
+        )
+        .unwrap();
+        validate_mpkg_with_options(&input, &options).unwrap();
+        let out = dir.path().join("verified");
+        unpack_with_options(&input, &out, &options).unwrap();
+        assert_eq!(fs::read(out.join("crc/crc.bin")).unwrap(), data);
+
+        // 未知字段只能有一种含义
+        let options = UnpackOptions {
+            verify: true,
+            decompress: true,
+            ..quiet()
+        };
+        let error = validate_mpkg_with_options(&input, &options).unwrap_err();
+        assert!(matches!(error, MpkgError::InvalidOptions(_)), "{:?}", error);
+    }
+
+    #[cfg(feature = "compression")]
+    #[test]
+    fn decompress_flagged_entries() {
+        use flate2::write::DeflateEncoder;
+        use flate2::Compression;
+
+        let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(b"hello hello hello").unwrap();
+        let mut block = encoder.finish().unwrap();
+        block.extend_from_slice(&17u32.to_le_bytes());
+        let bytes = ArchiveBuilder::new("PKGV0001")
+            .entry(
+                b"a.txt",
+                crate::format::COMPRESSED_FLAG,
+                block.len() as u64,
+                &block,
+            )
+            .build();
+        let dir = tempfile::tempdir().unwrap();
+
+        let options = UnpackOptions {
+            decompress: true,
+            ..quiet()
+        };
+        unpack_bytes(&bytes, &dir.path().join("inflated"), &options).unwrap();
+        let inflated = fs::read(dir.path().join("inflated/out/a.txt")).unwrap();
+        assert_eq!(inflated, b"hello hello hello");
+
+        // 未启用 `decompress` 时原样写出数据块
+        unpack_bytes(&bytes, &dir.path().join("raw"), &quiet()).unwrap();
+        assert_eq!(fs::read(dir.path().join("raw/out/a.txt")).unwrap(), block);
+    }
+
+    #[test]
+    fn rename_closure_keeps_state() {
+        let bytes = ArchiveBuilder::new("PKGV0001")
+            .file("a.txt", b"a")
+            .file("skip.txt", b"s")
+            .file("b/c.txt", b"c")
+            .build();
+        let dir = tempfile::tempdir().unwrap();
+        let prefix = String::from("renamed");
+        let mut next = 0;
+        let options = UnpackOptions::builder()
+            .log_level(LogLevel::Quiet)
+            .rename(move |name| {
+                if name == "skip.txt" {
+                    return None;
+                }
+                next += 1;
+                Some(format!("{}/{}_{}", prefix, next, name))
+            })
+            .build()
+            .unwrap();
+        let report = unpack_bytes(&bytes, dir.path(), &options).unwrap();
+        assert_eq!(report.files_extracted, 2);
+        let out = dir.path().join("out/renamed");
+        assert_eq!(fs::read(out.join("1_a.txt")).unwrap(), b"a");
+        assert_eq!(fs::read(out.join("2_b/c.txt")).unwrap(), b"c");
+
+        // 安全检查作用于映射后的文件名
+        let options = UnpackOptions {
+            rename: Some(RenameHook::by_name(|name| Some(format!("../{}", name)))),
+            ..quiet()
+        };
+        let error = unpack_bytes(&bytes, &dir.path().join("unsafe"), &options).unwrap_err();
+        assert!(matches!(error, MpkgError::UnsafePath { .. }), "{:?}", error);
+    }
+
+    #[test]
+    fn route_by_entry_size() {
+        let bytes = ArchiveBuilder::new("PKGV0001")
+            .file("small.txt", b"s")
+            .file("big.bin", &[0; 64])
+            .build();
+        let dir = tempfile::tempdir().unwrap();
+        let big_disk = dir.path().join("big");
+        let target = big_disk.clone();
+        let options = UnpackOptions::builder()
+            .log_level(LogLevel::Quiet)
+            .allow_absolute_routes(true)
+            .route(move |entry| {
+                if entry.size >= 64 {
+                    Some(target.join(&entry.name))
+                } else {
+                    Some(PathBuf::from("small").join(&entry.name))
+                }
+            })
+            .build()
+            .unwrap();
+        unpack_bytes(&bytes, dir.path(), &options).unwrap();
+        assert_eq!(fs::read(big_disk.join("big.bin")).unwrap(), [0; 64]);
+        let small = dir.path().join("out/small/small.txt");
+        assert_eq!(fs::read(small).unwrap(), b"s");
+        assert!(!dir.path().join("out/big.bin").exists());
+    }
+
+    #[test]
+    fn manifest_marks_skipped_files_as_not_extracted() {
+        let bytes = ArchiveBuilder::new("PKGV0001")
+            .file("a.txt", b"new")
+            .file("b.txt", b"b")
+            .file("empty.txt", b"")
+            .build();
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("out")).unwrap();
+        fs::write(dir.path().join("out/a.txt"), b"old").unwrap();
+        let options = UnpackOptions {
+            overwrite: OverwritePolicy::Skip,
+            skip_empty: true,
+            manifest: true,
+            ..quiet()
+        };
+        unpack_bytes(&bytes, dir.path(), &options).unwrap();
+
+        // 已存在而被保留的文件与被跳过的空文件都没有写入
+        let manifest = fs::read_to_string(dir.path().join("out").join(MANIFEST_FILE_NAME)).unwrap();
+        for (name, extracted) in [("a.txt", false), ("b.txt", true), ("empty.txt", false)] {
+            let line = manifest
+                .lines()
+                .find(|line| line.contains(&format!("\"name\": \"{}\"", name)))
+                .unwrap();
+            assert!(
+                line.ends_with(&format!("\"extracted\": {}}}", extracted))
+                    || line.ends_with(&format!("\"extracted\": {}}},", extracted)),
+                "{}",
+                line
+            );
+        }
+        assert_eq!(fs::read(dir.path().join("out/a.txt")).unwrap(), b"old");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn refuse_symlinks_checks_absolute_routes() {
+        let bytes = ArchiveBuilder::new("PKGV0001").file("a.txt", b"a").build();
+        let dir = tempfile::tempdir().unwrap();
+        let real = dir.path().join("real");
+        fs::create_dir(&real).unwrap();
+        std::os::unix::fs::symlink(&real, dir.path().join("link")).unwrap();
+        let link = dir.path().join("link");
+        let options = UnpackOptions::builder()
+            .log_level(LogLevel::Quiet)
+            .allow_absolute_routes(true)
+            .refuse_symlinks(true)
+            .route(move |entry| Some(link.join(&entry.name)))
+            .build()
+            .unwrap();
+        let error = unpack_bytes(&bytes, dir.path(), &options).unwrap_err();
+        assert!(matches!(error, MpkgError::UnsafePath { .. }), "{:?}", error);
+        assert!(!real.join("a.txt").exists());
+    }
+}
@@ -0,0 +1,664 @@
+use std::cell::RefCell;
+use std::env;
+use std::fs;
+use std::io::{self, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use mpkg_unpack::{
+    archive_info, extract_file_with_options, find_archives, list_mpkg_json, output_within_input,
+    print_progress, unpack_dir_with_archive_progress, unpack_with_options_and_progress,
+    validate_mpkg_with_options, DirProgressEvent, DuplicatePolicy, ListFormat, LogLevel, MpkgError,
+    OverwritePolicy, ProgressEvent, ReservedNamePolicy, SizeOrder, UnpackOptions,
+};
+
+const USAGE: &str = "\
+用法: mpkg_unpack [--input <目录>] [--output <目录>] [文件.mpkg ...]
+       mpkg_unpack <文件.mpkg> --extract <包内文件名>
+       mpkg_unpack --info [--input <目录>] [文件.mpkg ...]
+       mpkg_unpack verify [--verify] [--input <目录>] [文件.mpkg ...]
+       mpkg_unpack            (不带参数时交互式输入路径)
+
+选项:
+  -i, --input <目录>    解包该目录下的所有MPKG文件
+  -o, --output <目录>   解包输出目录，省略时解包到系统临时目录下新建的文件夹
+  -x, --extract <名称>  将包内的单个文件写到标准输出
+      --ignore-case     --extract 查找文件名时不区分大小写
+      --info            只显示文件数量、总大小等统计信息，不解包
+      --format <格式>   --info 的输出格式：text（文本，默认）、json、ndjson（每行一个文件）、
+                        tsv（每行为制表符分隔的名称、偏移与大小）
+  -r, --recursive       同时解包 --input 子文件夹中的MPKG文件
+      --output-per-archive <true|false>  是否为每个MPKG文件创建子文件夹，默认 true
+      --subfolder <模板>  子文件夹的命名模板，可用 {stem}、{name}、{version}，默认 {stem}
+      --exclude <通配符>  跳过匹配的文件，可重复指定，例如 --exclude '*.bak'
+      --no-sort         按文件系统返回的顺序处理 --input 中的文件，不按路径排序
+      --largest <数量>  只解包最大的N个文件
+      --smallest <数量>  只解包最小的N个文件
+      --strip-prefix <路径>  去掉文件名开头的这段路径，例如 --strip-prefix assets
+      --lowercase       将输出路径转换为小写，仅大小写不同的文件按 --duplicates 处理
+      --flatten         丢弃目录结构，重名文件追加 _1、_2 等后缀
+      --manifest        在输出文件夹中写入 manifest.json
+      --index           在输出文件夹中写入 index.tsv，记录各文件在包内的偏移与大小
+      --max-files <数量>  文件表允许的最大文件数量，默认 500000
+      --max-total-size <大小>  待解包文件的总大小上限，可带 K/M 后缀，默认不限制
+      --buffer-size <大小>  复制数据的缓冲区大小，可带 K/M 后缀，默认 1M
+      --no-empty-folder  包内没有文件时不创建输出文件夹
+      --skip-empty      不为大小为0的文件创建空文件
+      --no-follow-symlinks  输出路径中存在符号链接时报错，不跟随链接写入
+      --resume          跳过已存在且大小正确的文件，继续上次中断的解包
+      --skip-unchanged  跳过已存在且CRC32与包内记录一致的文件，只更新改变的文件
+      --atomic          先解包到 <名称>.partial，全部成功后再重命名
+      --continue-on-error  单个文件失败时跳过该文件继续解包
+      --remove-partial  写入失败（例如磁盘已满）时删除写了一半的文件
+      --threads <数量>  解包使用的线程数，默认按CPU数量自动选择，较小的包总是逐个解包
+      --no-timestamps   不应用包内记录的修改时间
+      --mode <八进制>   在Unix上为解包出的文件设置权限，例如 644 或 755
+      --verify          校验文件内容的CRC32，要求未知字段保存的是CRC32
+      --decompress      解压未知字段最低位标记为压缩的文件，需要 compression 特性，不能与 --verify 同用
+      --overwrite <策略>  输出文件已存在时：overwrite（覆盖，默认）、skip（跳过）、error（报错）
+      --reserved-names <策略>  Windows上不可用的文件名：allow（原样）、error（报错）、rewrite（改写）
+      --duplicates <策略>  多个文件写到同一路径时：last（保留最后一个，默认）、first（保留第一个）、
+                        error（报错）、rename（追加序号）
+  -q, --quiet           只输出错误信息
+  -v, --verbose         输出每个文件的偏移与大小
+  -h, --help            显示此帮助
+
+verify 读取并校验每个包的文件表与全部数据，不写入任何文件，退出码：
+  0 全部通过，3 有包已损坏，4 读取时发生I/O错误";
+
+/// `verify` 发现包已损坏时的退出码
+const EXIT_CORRUPT: i32 = 3;
+
+/// `verify` 读取时发生I/O错误的退出码
+const EXIT_IO_ERROR: i32 = 4;
+
+/// 命令行参数
+#[derive(Debug, Default)]
+struct Args {
+    input: Option<PathBuf>,
+    output: Option<PathBuf>,
+    files: Vec<PathBuf>,
+    extract: Option<String>,
+    info: bool,
+    /// `verify` 子命令：只校验，不解包
+    validate: bool,
+    /// `--info` 以JSON输出时的格式，`None` 为便于阅读的文本
+    format: Option<ListFormat>,
+    options: UnpackOptions,
+}
+
+/// 解析带可选 K/M 后缀的字节数，例如 `64K`、`4M`
+fn parse_size(value: &str) -> Option<usize> {
+    let (digits, unit) = match value.char_indices().last()? {
+        (i, 'k' | 'K') => (&value[..i], 1024),
+        (i, 'm' | 'M') => (&value[..i], 1024 * 1024),
+        _ => (value, 1),
+    };
+    digits.parse::<usize>().ok()?.checked_mul(unit)
+}
+
+/// 解析命令行参数，出错时返回错误描述
+fn parse_args<I: Iterator<Item = String>>(args: I) -> Result<Args, String> {
+    let mut parsed = Args::default();
+    let mut args = args.peekable();
+    parsed.validate = args.next_if(|arg| arg == "verify").is_some();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                process::exit(0);
+            }
+            "-i" | "--input" => {
+                let value = args.next().ok_or_else(|| format!("{} 缺少参数值", arg))?;
+                parsed.input = Some(PathBuf::from(value));
+            }
+            "-o" | "--output" => {
+                let value = args.next().ok_or_else(|| format!("{} 缺少参数值", arg))?;
+                parsed.output = Some(PathBuf::from(value));
+            }
+            "-x" | "--extract" => {
+                let value = args.next().ok_or_else(|| format!("{} 缺少参数值", arg))?;
+                parsed.extract = Some(value);
+            }
+            "-q" | "--quiet" => parsed.options.log_level = LogLevel::Quiet,
+            "-v" | "--verbose" => parsed.options.log_level = LogLevel::Verbose,
+            "--info" => parsed.info = true,
+            "--format" => {
+                let value = args.next().ok_or_else(|| format!("{} 缺少参数值", arg))?;
+                parsed.format = match value.as_str() {
+                    "text" => None,
+                    "json" => Some(ListFormat::Json),
+                    "ndjson" => Some(ListFormat::Ndjson),
+                    "tsv" => Some(ListFormat::Tsv),
+                    _ => return Err(format!("无效的输出格式：{}", value)),
+                };
+            }
+            "-r" | "--recursive" => parsed.options.recursive = true,
+            "--output-per-archive" => {
+                let value = args.next().ok_or_else(|| format!("{} 缺少参数值", arg))?;
+                parsed.options.per_archive_folder = match value.as_str() {
+                    "true" => true,
+                    "false" => false,
+                    _ => return Err(format!("无效的取值：{}", value)),
+                };
+            }
+            "--subfolder" => {
+                let value = args.next().ok_or_else(|| format!("{} 缺少参数值", arg))?;
+                parsed.options.subfolder_template = Some(value);
+            }
+            "--exclude" => {
+                let value = args.next().ok_or_else(|| format!("{} 缺少参数值", arg))?;
+                parsed.options.exclude.push(value);
+            }
+            "--strip-prefix" => {
+                let value = args.next().ok_or_else(|| format!("{} 缺少参数值", arg))?;
+                parsed.options.strip_prefix = Some(value);
+            }
+            "--lowercase" => parsed.options.lowercase_names = true,
+            "--ignore-case" => parsed.options.ignore_case = true,
+            "--no-sort" => parsed.options.sort_scan = false,
+            "--flatten" => parsed.options.flatten = true,
+            "--manifest" => parsed.options.manifest = true,
+            "--verify" => parsed.options.verify = true,
+            "--decompress" => parsed.options.decompress = true,
+            "--index" => parsed.options.index_file = true,
+            "--no-empty-folder" => parsed.options.create_empty_folder = false,
+            "--skip-empty" => parsed.options.skip_empty = true,
+            "--no-follow-symlinks" => parsed.options.refuse_symlinks = true,
+            "--resume" => parsed.options.resume = true,
+            "--skip-unchanged" => parsed.options.skip_unchanged = true,
+            "--atomic" => parsed.options.atomic = true,
+            "--continue-on-error" => parsed.options.continue_on_error = true,
+            "--remove-partial" => parsed.options.remove_partial = true,
+            "--threads" => {
+                let value = args.next().ok_or_else(|| format!("{} 缺少参数值", arg))?;
+                parsed.options.threads = match value.parse() {
+                    Ok(0) | Err(_) => return Err(format!("无效的线程数：{}", value)),
+                    Ok(threads) => Some(threads),
+                };
+            }
+            "--no-timestamps" => parsed.options.preserve_timestamps = false,
+            "--mode" => {
+                let value = args.next().ok_or_else(|| format!("{} 缺少参数值", arg))?;
+                let digits = value.strip_prefix("0o").unwrap_or(&value);
+                parsed.options.default_mode = match u32::from_str_radix(digits, 8) {
+                    Ok(mode) if mode <= 0o7777 => Some(mode),
+                    _ => return Err(format!("无效的文件权限：{}", value)),
+                };
+            }
+            "--largest" | "--smallest" => {
+                let value = args.next().ok_or_else(|| format!("{} 缺少参数值", arg))?;
+                let n = value
+                    .parse()
+                    .map_err(|_| format!("无效的文件数量：{}", value))?;
+                parsed.options.extract_top_n = Some(n);
+                parsed.options.size_order = if arg == "--largest" {
+                    SizeOrder::Largest
+                } else {
+                    SizeOrder::Smallest
+                };
+            }
+            "--max-files" => {
+                let value = args.next().ok_or_else(|| format!("{} 缺少参数值", arg))?;
+                parsed.options.max_file_count = value
+                    .parse()
+                    .map_err(|_| format!("无效的文件数量上限：{}", value))?;
+            }
+            "--max-total-size" => {
+                let value = args.next().ok_or_else(|| format!("{} 缺少参数值", arg))?;
+                let limit =
+                    parse_size(&value).ok_or_else(|| format!("无效的总大小上限：{}", value))?;
+                parsed.options.max_total_size = Some(limit as u64);
+            }
+            "--buffer-size" => {
+                let value = args.next().ok_or_else(|| format!("{} 缺少参数值", arg))?;
+                parsed.options.buffer_size = match parse_size(&value) {
+                    Some(size) if size > 0 => size,
+                    _ => return Err(format!("无效的缓冲区大小：{}", value)),
+                };
+            }
+            "--overwrite" => {
+                let value = args.next().ok_or_else(|| format!("{} 缺少参数值", arg))?;
+                parsed.options.overwrite = match value.as_str() {
+                    "overwrite" => OverwritePolicy::Overwrite,
+                    "skip" => OverwritePolicy::Skip,
+                    "error" => OverwritePolicy::Error,
+                    _ => return Err(format!("无效的覆盖策略：{}", value)),
+                };
+            }
+            "--reserved-names" => {
+                let value = args.next().ok_or_else(|| format!("{} 缺少参数值", arg))?;
+                parsed.options.reserved_names = match value.as_str() {
+                    "allow" => ReservedNamePolicy::Allow,
+                    "error" => ReservedNamePolicy::Error,
+                    "rewrite" => ReservedNamePolicy::Rewrite,
+                    _ => return Err(format!("无效的文件名策略：{}", value)),
+                };
+            }
+            "--duplicates" => {
+                let value = args.next().ok_or_else(|| format!("{} 缺少参数值", arg))?;
+                parsed.options.duplicates = match value.as_str() {
+                    "last" => DuplicatePolicy::LastWins,
+                    "first" => DuplicatePolicy::FirstWins,
+                    "error" => DuplicatePolicy::Error,
+                    "rename" => DuplicatePolicy::Rename,
+                    _ => return Err(format!("无效的重名策略：{}", value)),
+                };
+            }
+            _ if arg.starts_with('-') => return Err(format!("未知选项：{}", arg)),
+            _ => parsed.files.push(PathBuf::from(arg)),
+        }
+    }
+
+    if parsed.validate && (parsed.info || parsed.extract.is_some()) {
+        return Err("verify 不能与 --info 或 --extract 一起使用".to_string());
+    }
+    if parsed.format.is_some() && !parsed.info {
+        return Err("--format 需要与 --info 一起使用".to_string());
+    }
+    if parsed.extract.is_some() {
+        if parsed.input.is_some() || parsed.files.len() != 1 {
+            return Err("--extract 需要且只能指定一个MPKG文件".to_string());
+        }
+        return Ok(parsed);
+    }
+    if parsed.input.is_none() && parsed.files.is_empty() {
+        return Err("需要指定 --input 目录或至少一个MPKG文件".to_string());
+    }
+    Ok(parsed)
+}
+
+/// `--info` 最多列出的未知字段取值数量
+const INFO_EXTRA_VALUES: usize = 5;
+
+/// 打印单个MPKG文件的统计信息
+fn print_info(path: &Path) {
+    let info = match archive_info(path) {
+        Ok(info) => info,
+        Err(e) => {
+            eprintln!("读取失败: {}: {}", path.display(), e);
+            return;
+        }
+    };
+    println!("{}", path.display());
+    println!("  格式版本：{}", info.version);
+    println!("  文件数量：{}", info.file_count);
+    println!("  数据总大小：{} 字节", info.total_bytes);
+    if let Some(largest) = &info.largest {
+        println!("  最大文件：{} ({} 字节)", largest.name, largest.size);
+    }
+    if !info.extra_values.is_empty() {
+        let common = info
+            .extra_values
+            .iter()
+            .take(INFO_EXTRA_VALUES)
+            .map(|(value, count)| format!("{:08x} ×{}", value, count))
+            .collect::<Vec<_>>()
+            .join("，");
+        println!(
+            "  未知字段：{} 种取值，最常见：{}",
+            info.extra_values.len(),
+            common
+        );
+    }
+    println!(
+        "  文件长度：{} 字节，{}",
+        info.archive_len,
+        if info.sizes_match {
+            "与文件表一致"
+        } else {
+            "与文件表不一致"
+        }
+    );
+}
+
+/// 以JSON输出文件表：`Json` 输出每个包一个对象组成的数组，`Ndjson` 每行输出一个文件
+///
+/// 读取失败的包只在标准错误中报告，不影响其余输出。
+fn print_json(paths: &[PathBuf], format: ListFormat) {
+    let listings = paths
+        .iter()
+        .filter_map(|path| match list_mpkg_json(path, format) {
+            Ok(json) => Some(json),
+            Err(e) => {
+                eprintln!("读取失败: {}: {}", path.display(), e);
+                None
+            }
+        });
+    if format != ListFormat::Json {
+        listings.for_each(|json| print!("{}", json));
+        return;
+    }
+    let listings: Vec<String> = listings.map(|json| json.trim_end().to_string()).collect();
+    if listings.is_empty() {
+        println!("[]");
+    } else {
+        println!("[\n{}\n]", listings.join(",\n"));
+    }
+}
+
+/// 速度行的最短刷新间隔
+const THROUGHPUT_REFRESH: Duration = Duration::from_millis(250);
+
+/// 在标准错误的同一行上显示当前包的解包百分比与速度
+///
+/// 标准错误不是终端时不显示任何内容；打印其他进度信息之前先清除该行，避免混在一起。
+struct Throughput {
+    enabled: bool,
+    total_bytes: u64,
+    started: Instant,
+    last_drawn: Option<Instant>,
+}
+
+impl Throughput {
+    fn new(enabled: bool) -> Throughput {
+        Throughput {
+            enabled: enabled && io::stderr().is_terminal(),
+            total_bytes: 0,
+            started: Instant::now(),
+            last_drawn: None,
+        }
+    }
+
+    /// 根据进度事件刷新或清除速度行
+    fn update(&mut self, event: &ProgressEvent) {
+        if !self.enabled {
+            return;
+        }
+        match event {
+            ProgressEvent::ArchiveOpened { total_bytes, .. } => {
+                self.clear();
+                self.total_bytes = *total_bytes;
+                self.started = Instant::now();
+            }
+            ProgressEvent::BytesCopied { total_bytes, .. } => {
+                if self
+                    .last_drawn
+                    .is_none_or(|drawn| drawn.elapsed() >= THROUGHPUT_REFRESH)
+                {
+                    self.draw(*total_bytes);
+                }
+            }
+            _ => self.clear(),
+        }
+    }
+
+    fn draw(&mut self, copied: u64) {
+        let percent = if self.total_bytes == 0 {
+            100.0
+        } else {
+            copied as f64 * 100.0 / self.total_bytes as f64
+        };
+        let seconds = self.started.elapsed().as_secs_f64().max(0.001);
+        let speed = copied as f64 / seconds / (1024.0 * 1024.0);
+        eprint!("\r{:5.1}%  {:.1} MB/s", percent, speed);
+        self.last_drawn = Some(Instant::now());
+    }
+
+    fn clear(&mut self) {
+        if self.last_drawn.take().is_some() {
+            eprint!("\r\x1b[K");
+        }
+    }
+}
+
+/// 未指定输出目录时，在系统临时目录下创建一个新的空文件夹并返回其路径
+///
+/// 逐个尝试候选名称并直接创建，已存在时换下一个，不会与同时运行的其他进程选中同一文件夹。
+fn temp_output_dir() -> io::Result<PathBuf> {
+    let seconds = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs());
+    let base = format!("mpkg_unpack-{}-{}", seconds, process::id());
+    let temp_dir = env::temp_dir();
+    let mut candidate = temp_dir.join(&base);
+    let mut suffix = 1;
+    loop {
+        match fs::create_dir(&candidate) {
+            Ok(()) => return Ok(candidate),
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {
+                candidate = temp_dir.join(format!("{}_{}", base, suffix));
+                suffix += 1;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// 交互式读取一行输入
+fn prompt(message: &str) -> io::Result<String> {
+    print!("{}", message);
+    io::stdout().flush()?; // 确保提示符被显示
+    let mut line = String::new();
+    io::stdin().read_line(&mut line)?;
+    Ok(line.trim().to_string())
+}
+
+/// 命令行中指定的MPKG文件，加上 `--input` 目录下找到的所有MPKG文件
+///
+/// 读取目录失败时打印错误并返回该错误，由调用方决定退出码。
+fn archive_paths(args: &Args) -> Result<Vec<PathBuf>, MpkgError> {
+    let mut paths = args.files.clone();
+    if let Some(input_folder_path) = args.input.as_deref() {
+        let archives = find_archives(input_folder_path, &args.options).inspect_err(|e| {
+            eprintln!("读取文件夹失败: {}: {}", input_folder_path.display(), e);
+        })?;
+        paths.extend(archives);
+    }
+    Ok(paths)
+}
+
+/// 逐个校验MPKG文件，每个包输出一行结果，返回退出码
+///
+/// 有I/O错误时返回 `EXIT_IO_ERROR`，否则有包损坏时返回 `EXIT_CORRUPT`，全部通过时返回0。
+fn verify_archives(paths: &[PathBuf], options: &UnpackOptions) -> i32 {
+    let mut exit_code = 0;
+    let mut failed = 0;
+    for path in paths {
+        match validate_mpkg_with_options(path, options) {
+            Ok(report) => println!(
+                "校验通过: {}（{}，{} 个文件，{} 字节）",
+                path.display(),
+                report.version,
+                report.file_count,
+                report.total_bytes
+            ),
+            Err(e) => {
+                let code = match e {
+                    MpkgError::Io(_) => EXIT_IO_ERROR,
+                    _ => EXIT_CORRUPT,
+                };
+                exit_code = exit_code.max(code);
+                failed += 1;
+                println!("校验失败: {}: {}", path.display(), e);
+            }
+        }
+    }
+    println!(
+        "共校验 {} 个文件，通过 {} 个，失败 {} 个",
+        paths.len(),
+        paths.len() - failed,
+        failed
+    );
+    exit_code
+}
+
+fn main() -> io::Result<()> {
+    let args = if env::args().len() > 1 {
+        match parse_args(env::args().skip(1)) {
+            Ok(args) => args,
+            Err(message) => {
+                eprintln!("{}\n\n{}", message, USAGE);
+                process::exit(2);
+            }
+        }
+    } else {
+        let input_folder = prompt("请输入包含MPKG文件的文件夹路径：")?;
+        let output_folder = prompt("请输入解包输出文件夹路径：")?;
+        Args {
+            input: Some(PathBuf::from(input_folder)),
+            output: Some(PathBuf::from(output_folder)),
+            files: Vec::new(),
+            extract: None,
+            info: false,
+            validate: false,
+            format: None,
+            options: UnpackOptions::default(),
+        }
+    };
+
+    // 提取单个文件到标准输出，日志只写到标准错误
+    if let Some(entry_name) = args.extract.as_deref() {
+        let archive = &args.files[0];
+        match extract_file_with_options(
+            archive,
+            entry_name,
+            &mut io::stdout().lock(),
+            &args.options,
+        ) {
+            Ok(true) => return Ok(()),
+            Ok(false) => eprintln!("包内没有该文件: {}", entry_name),
+            Err(e) => eprintln!("提取失败: {}: {}", archive.display(), e),
+        }
+        process::exit(1);
+    }
+
+    // 只校验，以退出码报告结果
+    if args.validate {
+        // 读取 `--input` 目录失败同样属于I/O错误
+        let code = match archive_paths(&args) {
+            Ok(paths) => verify_archives(&paths, &args.options),
+            Err(_) => EXIT_IO_ERROR,
+        };
+        process::exit(code);
+    }
+
+    // 只显示统计信息，不写入任何文件
+    if args.info {
+        let Ok(paths) = archive_paths(&args) else {
+            process::exit(1);
+        };
+        match args.format {
+            None => paths.iter().for_each(|path| print_info(path)),
+            Some(format) => print_json(&paths, format),
+        }
+        return Ok(());
+    }
+
+    // 指定输出路径，未指定时解包到临时目录，结束时打印该路径
+    let output_is_temp = args.output.is_none();
+    let output_path = match args.output.clone() {
+        Some(path) => path,
+        None => temp_output_dir()?,
+    };
+    let output_dir = output_path.as_path();
+
+    let show_progress = args.options.log_level > LogLevel::Quiet;
+
+    // 不创建子文件夹时，多个包中的同名文件会写到同一位置
+    if !args.options.per_archive_folder && (args.input.is_some() || args.files.len() > 1) {
+        eprintln!("警告: 多个MPKG文件将解包到同一文件夹，同名文件可能互相覆盖");
+    }
+
+    // 逐个解包命令行中指定的MPKG文件
+    for path in &args.files {
+        if show_progress {
+            println!("正在处理文件: {}", path.display());
+        }
+        let mut meter = Throughput::new(show_progress);
+        let mut print = print_progress(args.options.log_level);
+        let result =
+            unpack_with_options_and_progress(path.as_path(), output_dir, &args.options, |event| {
+                meter.update(&event);
+                print(event)
+            });
+        meter.clear();
+        match result {
+            Ok(report) if show_progress => println!(
+                "成功解包: {} -> {}（{} 个文件，{} 字节）",
+                path.display(),
+                report.output_dir.display(),
+                report.files_extracted,
+                report.bytes_written
+            ),
+            Ok(_) => {}
+            Err(e) => eprintln!("解包失败: {}: {}", path.display(), e),
+        }
+    }
+
+    if let Some(input_folder_path) = args.input.as_deref() {
+        // 检查文件夹是否存在
+        if !input_folder_path.is_dir() {
+            eprintln!("无效的文件夹路径！");
+            process::exit(1);
+        }
+
+        // 递归扫描时由库函数拒绝；否则只提醒解包出的文件会混入输入文件夹
+        if !args.options.recursive
+            && output_within_input(input_folder_path, output_dir).unwrap_or(false)
+        {
+            eprintln!("警告: 输出文件夹位于输入文件夹之内，解包出的文件会与MPKG文件混在一起");
+        }
+
+        // 遍历文件夹中的所有MPKG文件并解包，每个包结束时立即报告结果
+        let meter = RefCell::new(Throughput::new(show_progress));
+        let mut print = print_progress(args.options.log_level);
+        let (mut succeeded, mut failed, mut bytes_written) = (0, 0, 0);
+        let results = unpack_dir_with_archive_progress(
+            input_folder_path,
+            output_dir,
+            &args.options,
+            |event| match event {
+                DirProgressEvent::ArchiveStarted { path, index, total } => {
+                    if show_progress {
+                        println!("正在处理文件 [{}/{}]: {}", index + 1, total, path.display());
+                    }
+                }
+                DirProgressEvent::ArchiveFinished { path, result, .. } => {
+                    meter.borrow_mut().clear();
+                    match result {
+                        Ok(report) => {
+                            succeeded += 1;
+                            bytes_written += report.bytes_written;
+                            if show_progress {
+                                println!("成功解包: {}", path.display());
+                            }
+                        }
+                        Err(e) => {
+                            failed += 1;
+                            eprintln!("解包失败: {}: {}", path.display(), e);
+                        }
+                    }
+                }
+            },
+            |event| {
+                meter.borrow_mut().update(&event);
+                print(event)
+            },
+        );
+        meter.borrow_mut().clear();
+        if let Err(e) = results {
+            eprintln!("读取文件夹失败: {}: {}", input_folder_path.display(), e);
+            process::exit(1);
+        }
+        if show_progress {
+            println!(
+                "共处理 {} 个文件，成功 {} 个，失败 {} 个，写入 {} 字节",
+                succeeded + failed,
+                succeeded,
+                failed,
+                bytes_written
+            );
+        }
+    }
+
+    if output_is_temp {
+        let shown = output_dir
+            .canonicalize()
+            .unwrap_or_else(|_| output_path.clone());
+        println!("输出文件夹: {}", shown.display());
+    }
+    Ok(())
+}
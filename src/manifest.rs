@@ -0,0 +1,76 @@
+use std::fmt::Write as _;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::json::push_string;
+use crate::MpkgEntry;
+
+/// 清单文件名，写在每个包的输出文件夹中
+pub const MANIFEST_FILE_NAME: &str = "manifest.json";
+
+/// 索引文件名，写在每个包的输出文件夹中
+pub const INDEX_FILE_NAME: &str = "index.tsv";
+
+/// 生成记录文件表的JSON清单，各项严格按文件表顺序排列并记录其序号
+///
+/// `extracted` 与 `entries` 一一对应，记录该文件是否实际写入了输出文件夹。
+fn manifest_json(version: &str, entries: &[MpkgEntry], extracted: &[bool]) -> String {
+    let mut json = String::new();
+    json.push_str("{\n  \"version\": ");
+    push_string(&mut json, version);
+    json.push_str(",\n  \"entries\": [");
+    for (i, (entry, extracted)) in entries.iter().zip(extracted).enumerate() {
+        json.push_str(if i == 0 { "\n" } else { ",\n" });
+        let _ = write!(json, "    {{\"index\": {}, \"name\": ", entry.index);
+        push_string(&mut json, &entry.name);
+        let _ = write!(
+            json,
+            ", \"size\": {}, \"extra\": {}, \"offset\": {}, \"extracted\": {}}}",
+            entry.size, entry.extra, entry.offset, extracted
+        );
+    }
+    if !entries.is_empty() {
+        json.push_str("\n  ");
+    }
+    json.push_str("]\n}\n");
+    json
+}
+
+/// 生成 `name\toffset\tsize` 格式的索引，每行一项，按文件表顺序排列
+///
+/// 偏移为数据在源MPKG文件中的绝对位置；文件名中的 `\`、制表符、换行符与回车符分别写为 `\\`、`\t`、`\n` 与 `\r`。
+pub(crate) fn index_tsv(entries: &[MpkgEntry]) -> String {
+    let mut tsv = String::new();
+    for entry in entries {
+        for c in entry.name.chars() {
+            match c {
+                '\\' => tsv.push_str("\\\\"),
+                '\t' => tsv.push_str("\\t"),
+                '\n' => tsv.push_str("\\n"),
+                '\r' => tsv.push_str("\\r"),
+                c => tsv.push(c),
+            }
+        }
+        let _ = writeln!(tsv, "\t{}\t{}", entry.offset, entry.size);
+    }
+    tsv
+}
+
+/// 在输出文件夹中写入 `index.tsv`
+pub(crate) fn write_index(unpacked_folder: &Path, entries: &[MpkgEntry]) -> io::Result<()> {
+    fs::write(unpacked_folder.join(INDEX_FILE_NAME), index_tsv(entries))
+}
+
+/// 在输出文件夹中写入 `manifest.json`
+pub(crate) fn write_manifest(
+    unpacked_folder: &Path,
+    version: &str,
+    entries: &[MpkgEntry],
+    extracted: &[bool],
+) -> io::Result<()> {
+    fs::write(
+        unpacked_folder.join(MANIFEST_FILE_NAME),
+        manifest_json(version, entries, extracted),
+    )
+}
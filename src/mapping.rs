@@ -0,0 +1,48 @@
+use std::io;
+use std::path::Path;
+
+use memmap2::{Mmap, MmapOptions};
+
+use crate::{open_archive, MpkgError, UnpackOptions};
+
+/// 将名为 `name` 的文件数据映射到内存，映射范围恰好是 `[offset, offset + size)`
+///
+/// 不复制数据，适合只需计算摘要或解析内容的大文件；数据块按原样返回，不会解压。
+/// 文件名需与包内存储的名称完全一致，找不到时返回 `None`。
+/// 映射期间其他进程修改该文件会导致未定义行为，调用方需自行保证文件不被改动。
+pub fn map_entry<P: AsRef<Path>>(archive: P, name: &str) -> Result<Option<Mmap>, MpkgError> {
+    let (input_stream, table) = open_archive(archive.as_ref(), &UnpackOptions::default())?;
+    let Some(entry) = table.entries.iter().find(|entry| entry.name == name) else {
+        return Ok(None);
+    };
+
+    // 映射超出文件末尾的范围在访问时会导致程序崩溃，必须先检查
+    let entry_end = entry.offset.saturating_add(entry.size);
+    if entry_end > table.data_limit {
+        return Err(MpkgError::DataTruncated {
+            expected: entry_end,
+            got: table.data_limit,
+            offset: table.data_limit,
+        });
+    }
+    // 数据完整但超出本平台可映射的长度，不是截断
+    let len = usize::try_from(entry.size).map_err(|_| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "文件过大，无法映射到内存：{}（{} 字节，数据位于偏移 {}）",
+                entry.name, entry.size, entry.offset
+            ),
+        )
+    })?;
+
+    let file = input_stream.into_inner();
+    // SAFETY: 映射范围已确认位于文件之内，只读映射；文件在映射期间不被修改由调用方保证
+    let map = unsafe {
+        MmapOptions::new()
+            .offset(entry.offset)
+            .len(len)
+            .map(&file)?
+    };
+    Ok(Some(map))
+}
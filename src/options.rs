@@ -0,0 +1,728 @@
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::AtomicBool;
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use crate::{expand_folder_template, MpkgEntry, MpkgError};
+
+/// 解码头部与文件名时使用的编码
+#[derive(Debug, Clone, Copy, Default)]
+pub enum FilenameEncoding {
+    /// 严格按UTF-8解码，遇到无效字节时报错
+    Utf8Strict,
+    /// 按UTF-8解码，无效字节替换为 U+FFFD
+    #[default]
+    Utf8Lossy,
+    /// 按Shift-JIS解码，常见于日本游戏的资源包
+    #[cfg(feature = "shift-jis")]
+    ShiftJis,
+    /// 自定义解码函数，返回 `None` 表示无法解码
+    Custom(fn(&[u8]) -> Option<String>),
+}
+
+impl FilenameEncoding {
+    /// 解码一段文件名，失败时返回首个无法解码的字节在 `bytes` 中的位置
+    pub(crate) fn decode(&self, bytes: &[u8]) -> Result<String, usize> {
+        match self {
+            FilenameEncoding::Utf8Strict => std::str::from_utf8(bytes)
+                .map(str::to_string)
+                .map_err(|e| e.valid_up_to()),
+            FilenameEncoding::Utf8Lossy => Ok(String::from_utf8_lossy(bytes).to_string()),
+            #[cfg(feature = "shift-jis")]
+            FilenameEncoding::ShiftJis => {
+                let mut decoder = encoding_rs::SHIFT_JIS.new_decoder_without_bom_handling();
+                let capacity = decoder.max_utf8_buffer_length_without_replacement(bytes.len());
+                let mut name = String::with_capacity(capacity.unwrap_or(bytes.len()));
+                let mut decoded = 0;
+                loop {
+                    let rest = &bytes[decoded..];
+                    match decoder.decode_to_string_without_replacement(rest, &mut name, true) {
+                        (encoding_rs::DecoderResult::InputEmpty, _) => return Ok(name),
+                        // 已读取的部分末尾是无效字节及其后被一并读取的字节
+                        (encoding_rs::DecoderResult::Malformed(bad, extra), read) => {
+                            return Err(decoded + read - usize::from(bad) - usize::from(extra));
+                        }
+                        // 无法预先算出上限时空间可能不足，扩充后继续解码剩余部分
+                        (encoding_rs::DecoderResult::OutputFull, read) => {
+                            decoded += read;
+                            name.reserve(rest.len() - read + 16);
+                        }
+                    }
+                }
+            }
+            // 自定义解码无法给出具体位置，按文件名开头报告
+            FilenameEncoding::Custom(decode) => decode(bytes).ok_or(0),
+        }
+    }
+}
+
+/// `UnpackOptions::rename` 使用的输出路径映射闭包，可以捕获并修改外部状态
+///
+/// 克隆得到的选项共用同一个闭包，调用时加锁，因此闭包只需满足 `Send`。
+#[derive(Clone)]
+pub struct RenameHook(Arc<Mutex<RenameFn>>);
+
+/// `RenameHook` 包装的闭包类型
+type RenameFn = dyn FnMut(&MpkgEntry) -> Option<PathBuf> + Send;
+
+impl RenameHook {
+    /// 包装一个根据完整的文件表项（包括大小与未知字段）决定输出路径的闭包
+    pub fn new<F>(rename: F) -> Self
+    where
+        F: FnMut(&MpkgEntry) -> Option<PathBuf> + Send + 'static,
+    {
+        RenameHook(Arc::new(Mutex::new(rename)))
+    }
+
+    /// 包装一个只根据包内文件名决定输出路径的闭包
+    pub fn by_name<F>(mut rename: F) -> Self
+    where
+        F: FnMut(&str) -> Option<String> + Send + 'static,
+    {
+        RenameHook::new(move |entry| rename(&entry.name).map(PathBuf::from))
+    }
+
+    /// 调用映射闭包；闭包曾经panic时仍继续使用它
+    pub(crate) fn call(&self, entry: &MpkgEntry) -> Option<PathBuf> {
+        let mut rename = self
+            .0
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        rename(entry)
+    }
+}
+
+impl fmt::Debug for RenameHook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("RenameHook(..)")
+    }
+}
+
+/// 输出文件已存在时的处理方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OverwritePolicy {
+    /// 覆盖已存在的文件
+    #[default]
+    Overwrite,
+    /// 保留已存在的文件并跳过该文件的数据
+    Skip,
+    /// 中止解包并返回 `MpkgError::OutputExists`
+    Error,
+}
+
+/// 解包时打印到终端的信息量
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LogLevel {
+    /// 只打印错误
+    Quiet,
+    /// 打印每个文件的解包进度
+    #[default]
+    Normal,
+    /// 在 `Normal` 的基础上打印每个文件的偏移与大小
+    Verbose,
+}
+
+/// 遇到Windows保留设备名（如 `CON`、`COM1`）、非法字符或以点、空格结尾的文件名时的处理方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReservedNamePolicy {
+    /// 原样使用
+    Allow,
+    /// 返回 `MpkgError::ReservedName`
+    Error,
+    /// 改写为可用的名称：非法字符替换为 `_`，去掉结尾的点与空格，保留设备名前追加 `_`
+    Rewrite,
+}
+
+impl Default for ReservedNamePolicy {
+    /// Windows上默认改写，其余平台默认原样使用
+    fn default() -> Self {
+        if cfg!(windows) {
+            ReservedNamePolicy::Rewrite
+        } else {
+            ReservedNamePolicy::Allow
+        }
+    }
+}
+
+/// 多个文件写到同一输出路径时的处理方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DuplicatePolicy {
+    /// 只写入文件表中最后出现的文件
+    #[default]
+    LastWins,
+    /// 只写入文件表中最先出现的文件
+    FirstWins,
+    /// 返回 `MpkgError::DuplicateEntry`
+    Error,
+    /// 后出现的文件在扩展名前追加 `_1`、`_2` 等后缀，例如 `a.png` -> `a_1.png`
+    Rename,
+}
+
+/// `extract_top_n` 按大小挑选文件的方向
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SizeOrder {
+    /// 挑选最大的文件
+    #[default]
+    Largest,
+    /// 挑选最小的文件
+    Smallest,
+}
+
+/// 默认的数据复制缓冲区大小 (1MB)
+pub const DEFAULT_BUFFER_SIZE: usize = 1024 * 1024;
+
+/// 默认允许的最大头部长度 (1MB)
+pub const DEFAULT_MAX_HEADER_LENGTH: usize = 1024 * 1024;
+
+/// 默认允许的最大文件名长度 (64KB)
+pub const DEFAULT_MAX_NAME_LENGTH: usize = 64 * 1024;
+
+/// 默认允许的最大文件数量
+pub const DEFAULT_MAX_FILE_COUNT: usize = 500_000;
+
+/// `unpack_to_map` 默认允许读入内存的总大小 (256MB)
+pub const DEFAULT_MAX_MAP_SIZE: u64 = 256 * 1024 * 1024;
+
+/// 扫描文件夹时默认识别的扩展名
+pub const DEFAULT_EXTENSIONS: &[&str] = &["mpkg"];
+
+/// 解包选项，`Default` 与 `unpack_mpkg` 的行为一致
+#[derive(Debug, Clone)]
+pub struct UnpackOptions {
+    /// 头部与文件名的编码
+    pub encoding: FilenameEncoding,
+    /// 单独为头部的格式版本字符串指定编码，`None` 时与文件名相同使用 `encoding`
+    pub header_encoding: Option<FilenameEncoding>,
+    /// 只解包文件名匹配该通配符的文件，其余文件直接跳过
+    pub filter: Option<String>,
+    /// 跳过文件名匹配其中任一通配符的文件，优先于 `filter`
+    pub exclude: Vec<String>,
+    /// 只解包按 `size_order` 排列的前N个文件，其余文件直接跳过其数据
+    ///
+    /// 在 `filter`、`exclude` 与重名处理之后挑选，大小相同时文件表中靠前的优先。
+    pub extract_top_n: Option<usize>,
+    /// `extract_top_n` 挑选最大还是最小的文件
+    pub size_order: SizeOrder,
+    /// 将未知字段视为文件内容的CRC32并在解包时校验
+    pub verify: bool,
+    /// 将未知字段的最低位视为deflate压缩标记，解压被标记的文件
+    ///
+    /// 需要启用 `compression` 特性。未知字段只能有一种含义，因此不能与 `verify` 同时设置；
+    /// 默认关闭，未知字段保存CRC32等其他内容的包不会被误当作压缩包解压。
+    pub decompress: bool,
+    /// 丢弃目录结构，把所有文件直接写入输出文件夹
+    ///
+    /// 重名文件按在文件表中出现的顺序在扩展名前追加 `_1`、`_2` 等后缀，
+    /// 例如 `a/icon.png` 与 `b/icon.png` 分别写为 `icon.png` 与 `icon_1.png`。
+    pub flatten: bool,
+    /// 解包完成后在输出文件夹中写入记录文件表的 `manifest.json`
+    ///
+    /// 清单按文件表顺序列出所有文件（包括被跳过的文件），每项带有其在文件表中的序号 `index`；
+    /// 清单在所有文件解包之后写入，会覆盖包内同名的文件。
+    pub manifest: bool,
+    /// 解包完成后在输出文件夹中写入 `index.tsv`，每行为 `name\toffset\tsize`
+    ///
+    /// 与清单一样列出所有文件，偏移为数据在源MPKG文件中的绝对位置，
+    /// 便于其他工具不解析文件表就能直接读取源文件中的数据。
+    pub index_file: bool,
+    /// 输出文件已存在时的处理方式
+    pub overwrite: OverwritePolicy,
+    /// 扫描文件夹时识别为MPKG文件的扩展名（不含点，不区分大小写）
+    pub extensions: Vec<String>,
+    /// 扫描文件夹时递归进入子文件夹，并在输出文件夹中保留相同的目录结构
+    pub recursive: bool,
+    /// 扫描文件夹时按路径排序，使每次运行的处理顺序一致；关闭后按文件系统返回的顺序
+    pub sort_scan: bool,
+    /// 复制文件数据时使用的缓冲区大小，必须大于0
+    pub buffer_size: usize,
+    /// 包内记录了修改时间时将其应用到输出文件；关闭后输出文件保留解包时的时间
+    pub preserve_timestamps: bool,
+    /// 在Unix上为每个解包出的文件设置的权限位，例如 `0o755`；`None` 时保留系统默认权限
+    ///
+    /// 当前格式的各个版本都不记录文件权限，因此该值作用于所有文件；其他平台忽略此选项。
+    pub default_mode: Option<u32>,
+    /// 根据文件表项决定输出路径，返回 `None` 表示跳过该文件
+    ///
+    /// 在 `filter` 之后、`flatten` 之前调用。相对路径经过安全检查并放在输出文件夹下；
+    /// 绝对路径只有启用 `allow_absolute_routes` 时才原样使用，否则返回 `MpkgError::UnsafePath`。
+    pub rename: Option<RenameHook>,
+    /// 允许 `rename` 返回绝对路径，这些文件不经过 `strip_prefix`、`lowercase_names` 与 `flatten`，
+    /// 也不会被写入 `atomic` 的临时文件夹
+    pub allow_absolute_routes: bool,
+    /// 去掉文件名开头的这段路径，例如 `assets` 把 `assets/a/b.png` 写为 `a/b.png`
+    ///
+    /// 按路径的各部分比较，在安全检查之后、`flatten` 之前应用；
+    /// 不以该路径开头或与之完全相同的文件名保持不变。
+    pub strip_prefix: Option<String>,
+    /// 将输出路径转换为小写，在安全检查与 `strip_prefix` 之后、`flatten` 之前应用
+    ///
+    /// 仅大小写不同的文件会写到同一路径，按 `duplicates` 处理。
+    pub lowercase_names: bool,
+    /// 将文件名中的 `\` 视为目录分隔符，还原在Windows下打包的目录结构
+    pub normalize_separators: bool,
+    /// Windows上不可用的文件名的处理方式
+    pub reserved_names: ReservedNamePolicy,
+    /// 多个文件写到同一输出路径时的处理方式，在 `rename` 与 `flatten` 之后判断
+    pub duplicates: DuplicatePolicy,
+    /// 跳过大小为0的文件，不为其创建空文件
+    pub skip_empty: bool,
+    /// 输出文件或其上级文件夹是已存在的符号链接时返回 `MpkgError::UnsafePath`，而不是跟随链接写入
+    ///
+    /// 输出文件夹内的文件只检查输出文件夹以下的部分；`rename` 给出的绝对路径检查其全部上级文件夹。
+    pub refuse_symlinks: bool,
+    /// 继续上次中断的解包：已存在且大小与文件表一致的文件直接跳过，其余文件重新写入
+    ///
+    /// 启用后忽略 `overwrite`，大小不符的已存在文件总会被覆盖。
+    pub resume: bool,
+    /// 增量更新：已存在、大小一致且内容的CRC32与未知字段记录的值相同的文件直接跳过，其余文件覆盖
+    ///
+    /// 只适用于未知字段保存CRC32的包（即可用 `verify` 选项解包的包）。
+    /// 启用后忽略 `overwrite`，并优先于只比较大小的 `resume`。
+    pub skip_unchanged: bool,
+    /// 在输出文件夹下为每个MPKG文件创建以其文件名命名的子文件夹；关闭后直接写入输出文件夹
+    ///
+    /// 关闭后多个包中的同名文件会互相覆盖（或按 `overwrite` 处理），且不能与 `atomic` 同时使用。
+    pub per_archive_folder: bool,
+    /// 子文件夹的命名模板，可使用 `{stem}`（去掉扩展名的文件名）、`{name}`（完整文件名）
+    /// 与 `{version}`（头部的格式版本），例如 `{stem}_{version}`；`None` 时等同于 `{stem}`
+    ///
+    /// 替换后必须是单个非空的文件夹名，不能包含目录分隔符、`.` 或 `..`。
+    pub subfolder_template: Option<String>,
+    /// 先解包到同级的 `<文件夹名>.partial`，全部成功后再重命名为最终文件夹，失败时删除临时文件夹
+    pub atomic: bool,
+    /// 单个文件复制失败时记录错误并跳过该文件继续解包，文件表解析失败仍会中止
+    pub continue_on_error: bool,
+    /// 写入单个文件失败时删除写了一半的输出文件，避免留下内容不完整的文件
+    pub remove_partial: bool,
+    /// 从文件解包时使用的线程数，`None` 时取 `available_parallelism`
+    ///
+    /// 要解包的文件与数据都较少时仍逐个解包，省去创建线程的开销；
+    /// 并行解包时不同文件的进度事件可能交错出现。
+    pub threads: Option<usize>,
+    /// 取消标志，被置位后解包在当前数据块或下一个文件之前停止并返回 `MpkgError::Cancelled`
+    ///
+    /// 与 `atomic` 同时使用时已写入的临时文件夹会被删除。
+    pub cancel: Option<Arc<AtomicBool>>,
+    /// 打印到终端的信息量，只影响会打印进度的函数
+    pub log_level: LogLevel,
+    /// 包内没有任何文件时仍创建（空的）输出文件夹
+    pub create_empty_folder: bool,
+    /// 头部允许的最大长度，超出时返回 `MpkgError::BadHeader` 且不会为其分配内存
+    pub max_header_length: usize,
+    /// 文件名允许的最大长度，超出时返回 `MpkgError::NameTooLong` 且不会为其分配内存
+    pub max_name_length: usize,
+    /// 文件表允许的最大文件数量，超出时返回 `MpkgError::BadHeader`
+    pub max_file_count: usize,
+    /// 待解包文件按文件表记录的总大小上限，超出时在写入任何文件之前返回 `MpkgError::TotalSizeExceeded`
+    pub max_total_size: Option<u64>,
+    /// 按名称提取单个文件时不区分大小写
+    pub ignore_case: bool,
+}
+
+impl Default for UnpackOptions {
+    fn default() -> Self {
+        UnpackOptions {
+            encoding: FilenameEncoding::default(),
+            header_encoding: None,
+            filter: None,
+            exclude: Vec::new(),
+            extract_top_n: None,
+            size_order: SizeOrder::default(),
+            verify: false,
+            decompress: false,
+            flatten: false,
+            manifest: false,
+            index_file: false,
+            overwrite: OverwritePolicy::default(),
+            extensions: DEFAULT_EXTENSIONS
+                .iter()
+                .map(|ext| ext.to_string())
+                .collect(),
+            recursive: false,
+            sort_scan: true,
+            buffer_size: DEFAULT_BUFFER_SIZE,
+            preserve_timestamps: true,
+            default_mode: None,
+            rename: None,
+            allow_absolute_routes: false,
+            strip_prefix: None,
+            lowercase_names: false,
+            normalize_separators: true,
+            reserved_names: ReservedNamePolicy::default(),
+            duplicates: DuplicatePolicy::default(),
+            skip_empty: false,
+            refuse_symlinks: false,
+            resume: false,
+            skip_unchanged: false,
+            per_archive_folder: true,
+            subfolder_template: None,
+            atomic: false,
+            continue_on_error: false,
+            remove_partial: false,
+            threads: None,
+            cancel: None,
+            log_level: LogLevel::default(),
+            create_empty_folder: true,
+            max_header_length: DEFAULT_MAX_HEADER_LENGTH,
+            max_name_length: DEFAULT_MAX_NAME_LENGTH,
+            max_file_count: DEFAULT_MAX_FILE_COUNT,
+            max_total_size: None,
+            ignore_case: false,
+        }
+    }
+}
+
+impl UnpackOptions {
+    /// 创建以默认选项为起点的构建器
+    pub fn builder() -> UnpackOptionsBuilder {
+        UnpackOptionsBuilder::default()
+    }
+
+    /// 检查选项的取值是否有效
+    pub(crate) fn validate(&self) -> Result<(), MpkgError> {
+        if self.buffer_size == 0 {
+            return Err(MpkgError::InvalidOptions("缓冲区大小必须大于0".to_string()));
+        }
+        if self.atomic && !self.per_archive_folder {
+            return Err(MpkgError::InvalidOptions(
+                "atomic 需要为每个包创建单独的子文件夹".to_string(),
+            ));
+        }
+        if let Some(template) = &self.subfolder_template {
+            // 用示例文件名试替换一次，提前发现未知的占位符与不安全的文件夹名
+            expand_folder_template(template, Path::new("archive.mpkg"), "MPKG0001")?;
+        }
+        if self.decompress && !cfg!(feature = "compression") {
+            return Err(MpkgError::InvalidOptions(
+                "decompress 需要启用 compression 特性".to_string(),
+            ));
+        }
+        if self.decompress && self.verify {
+            return Err(MpkgError::InvalidOptions(
+                "decompress 与 verify 对未知字段的解释冲突，不能同时设置".to_string(),
+            ));
+        }
+        if self.threads == Some(0) {
+            return Err(MpkgError::InvalidOptions("线程数必须大于0".to_string()));
+        }
+        if self.default_mode.is_some_and(|mode| mode > 0o7777) {
+            return Err(MpkgError::InvalidOptions("文件权限超出 0o7777".to_string()));
+        }
+        Ok(())
+    }
+
+    /// 是否按压缩标记解压该文件：启用 `decompress` 且未知字段带有压缩标记
+    pub(crate) fn inflates(&self, entry: &MpkgEntry) -> bool {
+        cfg!(feature = "compression") && self.decompress && entry.is_compressed()
+    }
+
+    /// 实际使用的线程数：`threads` 未设置时取当前环境可用的并行数，无法获取时为1
+    pub fn thread_count(&self) -> usize {
+        self.threads
+            .unwrap_or_else(|| thread::available_parallelism().map_or(1, |threads| threads.get()))
+    }
+
+    /// 判断路径的扩展名是否属于 `extensions`，不区分大小写
+    ///
+    /// 启用 `compression` 特性时还识别在这些扩展名之后加上 `.gz` 的文件，例如 `a.mpkg.gz`。
+    pub(crate) fn has_archive_extension(&self, path: &Path) -> bool {
+        let Some(extension) = path.extension().and_then(|ext| ext.to_str()) else {
+            return false;
+        };
+        #[cfg(feature = "compression")]
+        if extension.eq_ignore_ascii_case("gz") {
+            return path
+                .file_stem()
+                .is_some_and(|stem| self.has_archive_extension(Path::new(stem)));
+        }
+        self.extensions
+            .iter()
+            .any(|known| known.eq_ignore_ascii_case(extension))
+    }
+}
+
+/// 逐项设置 `UnpackOptions` 的构建器，由 `UnpackOptions::builder` 创建
+///
+/// 未设置的选项保持默认值，`build` 时检查取值是否有效。
+#[derive(Debug, Clone, Default)]
+pub struct UnpackOptionsBuilder {
+    options: UnpackOptions,
+}
+
+impl UnpackOptionsBuilder {
+    /// 设置头部与文件名的编码
+    pub fn encoding(mut self, encoding: FilenameEncoding) -> Self {
+        self.options.encoding = encoding;
+        self
+    }
+
+    /// 单独设置头部格式版本字符串的编码
+    pub fn header_encoding(mut self, encoding: FilenameEncoding) -> Self {
+        self.options.header_encoding = Some(encoding);
+        self
+    }
+
+    /// 只解包文件名匹配该通配符的文件
+    pub fn filter<S: Into<String>>(mut self, pattern: S) -> Self {
+        self.options.filter = Some(pattern.into());
+        self
+    }
+
+    /// 只解包按 `order` 排列的前 `n` 个文件
+    pub fn extract_top_n(mut self, n: usize, order: SizeOrder) -> Self {
+        self.options.extract_top_n = Some(n);
+        self.options.size_order = order;
+        self
+    }
+
+    /// 追加一个排除的通配符
+    pub fn exclude<S: Into<String>>(mut self, pattern: S) -> Self {
+        self.options.exclude.push(pattern.into());
+        self
+    }
+
+    /// 是否校验文件内容的CRC32
+    pub fn verify(mut self, verify: bool) -> Self {
+        self.options.verify = verify;
+        self
+    }
+
+    /// 是否按未知字段中的压缩标记解压文件
+    pub fn decompress(mut self, decompress: bool) -> Self {
+        self.options.decompress = decompress;
+        self
+    }
+
+    /// 是否丢弃目录结构
+    pub fn flatten(mut self, flatten: bool) -> Self {
+        self.options.flatten = flatten;
+        self
+    }
+
+    /// 是否写入 `manifest.json`
+    pub fn manifest(mut self, manifest: bool) -> Self {
+        self.options.manifest = manifest;
+        self
+    }
+
+    /// 是否写入 `index.tsv`
+    pub fn index_file(mut self, index_file: bool) -> Self {
+        self.options.index_file = index_file;
+        self
+    }
+
+    /// 设置输出文件已存在时的处理方式
+    pub fn overwrite(mut self, policy: OverwritePolicy) -> Self {
+        self.options.overwrite = policy;
+        self
+    }
+
+    /// 设置扫描文件夹时识别的扩展名，替换默认列表
+    pub fn extensions<I, S>(mut self, extensions: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.options.extensions = extensions.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// 扫描文件夹时是否递归进入子文件夹
+    pub fn recursive(mut self, recursive: bool) -> Self {
+        self.options.recursive = recursive;
+        self
+    }
+
+    /// 扫描文件夹时是否按路径排序
+    pub fn sort_scan(mut self, sort: bool) -> Self {
+        self.options.sort_scan = sort;
+        self
+    }
+
+    /// 设置复制文件数据时使用的缓冲区大小
+    pub fn buffer_size(mut self, size: usize) -> Self {
+        self.options.buffer_size = size;
+        self
+    }
+
+    /// 是否应用包内记录的修改时间
+    pub fn preserve_timestamps(mut self, preserve: bool) -> Self {
+        self.options.preserve_timestamps = preserve;
+        self
+    }
+
+    /// 设置在Unix上应用到解包文件的权限位
+    pub fn default_mode(mut self, mode: u32) -> Self {
+        self.options.default_mode = Some(mode);
+        self
+    }
+
+    /// 设置只根据包内文件名决定输出路径的映射闭包
+    pub fn rename<F>(mut self, rename: F) -> Self
+    where
+        F: FnMut(&str) -> Option<String> + Send + 'static,
+    {
+        self.options.rename = Some(RenameHook::by_name(rename));
+        self
+    }
+
+    /// 设置按文件表项决定输出路径的映射闭包，与 `rename` 设置的是同一个选项，后设置的生效
+    pub fn route<F>(mut self, route: F) -> Self
+    where
+        F: FnMut(&MpkgEntry) -> Option<PathBuf> + Send + 'static,
+    {
+        self.options.rename = Some(RenameHook::new(route));
+        self
+    }
+
+    /// 是否允许 `rename` 返回绝对路径
+    pub fn allow_absolute_routes(mut self, allow: bool) -> Self {
+        self.options.allow_absolute_routes = allow;
+        self
+    }
+
+    /// 设置要从文件名开头去掉的路径
+    pub fn strip_prefix<S: Into<String>>(mut self, prefix: S) -> Self {
+        self.options.strip_prefix = Some(prefix.into());
+        self
+    }
+
+    /// 是否将输出路径转换为小写
+    pub fn lowercase_names(mut self, lowercase: bool) -> Self {
+        self.options.lowercase_names = lowercase;
+        self
+    }
+
+    /// 是否将文件名中的 `\` 视为目录分隔符
+    pub fn normalize_separators(mut self, normalize: bool) -> Self {
+        self.options.normalize_separators = normalize;
+        self
+    }
+
+    /// 设置Windows上不可用的文件名的处理方式
+    pub fn reserved_names(mut self, policy: ReservedNamePolicy) -> Self {
+        self.options.reserved_names = policy;
+        self
+    }
+
+    /// 设置多个文件写到同一输出路径时的处理方式
+    pub fn duplicates(mut self, policy: DuplicatePolicy) -> Self {
+        self.options.duplicates = policy;
+        self
+    }
+
+    /// 是否跳过大小为0的文件
+    pub fn skip_empty(mut self, skip: bool) -> Self {
+        self.options.skip_empty = skip;
+        self
+    }
+
+    /// 是否拒绝写入已存在的符号链接
+    pub fn refuse_symlinks(mut self, refuse: bool) -> Self {
+        self.options.refuse_symlinks = refuse;
+        self
+    }
+
+    /// 是否跳过已完整解包的文件，继续上次中断的解包
+    pub fn resume(mut self, resume: bool) -> Self {
+        self.options.resume = resume;
+        self
+    }
+
+    /// 是否跳过内容未改变（CRC32一致）的已存在文件
+    pub fn skip_unchanged(mut self, skip_unchanged: bool) -> Self {
+        self.options.skip_unchanged = skip_unchanged;
+        self
+    }
+
+    /// 是否为每个MPKG文件创建单独的子文件夹
+    pub fn per_archive_folder(mut self, per_archive_folder: bool) -> Self {
+        self.options.per_archive_folder = per_archive_folder;
+        self
+    }
+
+    /// 设置子文件夹的命名模板
+    pub fn subfolder_template<S: Into<String>>(mut self, template: S) -> Self {
+        self.options.subfolder_template = Some(template.into());
+        self
+    }
+
+    /// 是否先解包到临时文件夹，成功后再移动到最终位置
+    pub fn atomic(mut self, atomic: bool) -> Self {
+        self.options.atomic = atomic;
+        self
+    }
+
+    /// 单个文件失败时是否继续解包其余文件
+    pub fn continue_on_error(mut self, continue_on_error: bool) -> Self {
+        self.options.continue_on_error = continue_on_error;
+        self
+    }
+
+    /// 写入单个文件失败时是否删除写了一半的输出文件
+    pub fn remove_partial(mut self, remove_partial: bool) -> Self {
+        self.options.remove_partial = remove_partial;
+        self
+    }
+
+    /// 设置从文件解包时使用的线程数
+    pub fn threads(mut self, threads: usize) -> Self {
+        self.options.threads = Some(threads);
+        self
+    }
+
+    /// 设置取消标志
+    pub fn cancel(mut self, flag: Arc<AtomicBool>) -> Self {
+        self.options.cancel = Some(flag);
+        self
+    }
+
+    /// 设置打印到终端的信息量
+    pub fn log_level(mut self, level: LogLevel) -> Self {
+        self.options.log_level = level;
+        self
+    }
+
+    /// 包内没有文件时是否创建空的输出文件夹
+    pub fn create_empty_folder(mut self, create: bool) -> Self {
+        self.options.create_empty_folder = create;
+        self
+    }
+
+    /// 设置头部允许的最大长度
+    pub fn max_header_length(mut self, length: usize) -> Self {
+        self.options.max_header_length = length;
+        self
+    }
+
+    /// 设置文件名允许的最大长度
+    pub fn max_name_length(mut self, length: usize) -> Self {
+        self.options.max_name_length = length;
+        self
+    }
+
+    /// 设置文件表允许的最大文件数量
+    pub fn max_file_count(mut self, count: usize) -> Self {
+        self.options.max_file_count = count;
+        self
+    }
+
+    /// 设置待解包文件的总大小上限
+    pub fn max_total_size(mut self, limit: u64) -> Self {
+        self.options.max_total_size = Some(limit);
+        self
+    }
+
+    /// 按名称提取单个文件时是否不区分大小写
+    pub fn ignore_case(mut self, ignore_case: bool) -> Self {
+        self.options.ignore_case = ignore_case;
+        self
+    }
+
+    /// 检查选项并生成 `UnpackOptions`
+    pub fn build(self) -> Result<UnpackOptions, MpkgError> {
+        self.options.validate()?;
+        Ok(self.options)
+    }
+}
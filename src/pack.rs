@@ -0,0 +1,661 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::format::{check_signature, Layout, FOOTER_LEN};
+use crate::parse::assign_offsets;
+use crate::{
+    archive_folder_name, copy_stream_data, DuplicatePolicy, MpkgArchive, MpkgEntry, MpkgError,
+    DEFAULT_BUFFER_SIZE,
+};
+
+/// 打包时写入的默认格式版本
+pub const DEFAULT_VERSION: &str = "PKGV0001";
+
+/// 写入4字节的整数 (小端序)
+fn write_int32<W: Write>(writer: &mut W, value: u32) -> io::Result<()> {
+    writer.write_all(&value.to_le_bytes())
+}
+
+/// 写入带4字节长度前缀的字符串
+fn write_string<W: Write>(writer: &mut W, value: &str) -> io::Result<()> {
+    let length = u32::try_from(value.len()).map_err(|_| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("字符串过长：{}", value),
+        )
+    })?;
+    write_int32(writer, length)?;
+    writer.write_all(value.as_bytes())
+}
+
+/// 递归收集目录下的所有文件，按路径排序以保证打包结果稳定
+fn collect_files(dir: &Path, files: &mut Vec<PathBuf>) -> io::Result<()> {
+    let mut children = fs::read_dir(dir)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<io::Result<Vec<_>>>()?;
+    children.sort();
+
+    for path in children {
+        let file_type = fs::symlink_metadata(&path)?.file_type();
+        if file_type.is_dir() {
+            collect_files(&path, files)?;
+        } else if file_type.is_file() {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// 将文件相对于打包目录的路径转换为包内文件名，统一使用 `/` 分隔
+fn entry_name(input_dir: &Path, path: &Path) -> io::Result<String> {
+    let relative = path.strip_prefix(input_dir).map_err(|_| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("文件不在打包目录内：{}", path.display()),
+        )
+    })?;
+
+    let mut parts = Vec::new();
+    for component in relative.components() {
+        let part = component.as_os_str().to_str().ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("文件名不是有效的UTF-8：{}", path.display()),
+            )
+        })?;
+        parts.push(part);
+    }
+    Ok(parts.join("/"))
+}
+
+/// `pack_mpkg` 将要写出的MPKG文件布局
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PackLayout {
+    /// 写入头部的格式版本
+    pub version: String,
+    /// 按写入顺序排列的文件表，偏移为数据在输出文件中的位置
+    pub entries: Vec<MpkgEntry>,
+    /// 输出文件的总字节数
+    pub total_size: u64,
+}
+
+impl fmt::Display for PackLayout {
+    /// 每行一个文件：偏移、大小与文件名，最后一行为总大小
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for entry in &self.entries {
+            writeln!(f, "{:>12} {:>12} {}", entry.offset, entry.size, entry.name)?;
+        }
+        writeln!(
+            f,
+            "共 {} 个文件，输出 {} 字节",
+            self.entries.len(),
+            self.total_size
+        )
+    }
+}
+
+/// 文件的修改时间（Unix秒），无法获取时为 `None`
+fn modified_secs(metadata: &fs::Metadata) -> Option<u64> {
+    let modified = metadata.modified().ok()?;
+    Some(modified.duration_since(UNIX_EPOCH).ok()?.as_secs())
+}
+
+/// 收集打包目录下的文件并计算输出布局，返回与文件表一一对应的源文件路径
+///
+/// `table` 中出现的文件按其顺序排在前面并沿用未知字段与修改时间，其余文件按路径排序接在后面。
+/// `pack_mpkg` 与 `pack_mpkg_dry_run` 共用，保证预览的布局与实际写出的一致。
+fn plan_pack(
+    input_dir: &Path,
+    output_file: &Path,
+    version: &str,
+    table: &[MpkgEntry],
+) -> Result<(Vec<PathBuf>, PackLayout), MpkgError> {
+    check_signature(version.as_bytes())?;
+    let mut files = Vec::new();
+    collect_files(input_dir, &mut files)?;
+
+    // 输出文件位于打包目录内时不要把它自己打包进去
+    if let Ok(output_path) = fs::canonicalize(output_file) {
+        files.retain(|path| fs::canonicalize(path).map_or(true, |path| path != output_path));
+    }
+
+    let mut by_name = HashMap::with_capacity(files.len());
+    for path in files {
+        by_name.insert(entry_name(input_dir, &path)?, path);
+    }
+    let mut ordered = Vec::with_capacity(by_name.len());
+    for known in table {
+        if let Some(path) = by_name.remove(&known.name) {
+            ordered.push((path, Some(known)));
+        }
+    }
+    let mut rest: Vec<_> = by_name.into_values().collect();
+    rest.sort();
+    ordered.extend(rest.into_iter().map(|path| (path, None)));
+
+    // 文件表需要提前写入每个文件的大小
+    let layout = Layout::for_version(version);
+    let mut files = Vec::with_capacity(ordered.len());
+    let mut entries = Vec::with_capacity(ordered.len());
+    for (index, (path, known)) in ordered.into_iter().enumerate() {
+        let metadata = fs::metadata(&path)?;
+        let size = metadata.len();
+        if !layout.wide_sizes && u32::try_from(size).is_err() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("文件超过4GB：{}", path.display()),
+            )
+            .into());
+        }
+        entries.push(MpkgEntry {
+            index,
+            name: entry_name(input_dir, &path)?,
+            size,
+            offset: 0,
+            name_offset: 0,
+            extra: known.map_or(0, |known| known.extra),
+            modified: known
+                .and_then(|known| known.modified)
+                .or_else(|| modified_secs(&metadata)),
+        });
+        files.push(path);
+    }
+
+    let table_len: u64 = 4 + entries
+        .iter()
+        .map(|entry| table_entry_len(entry, layout))
+        .sum::<u64>();
+    let header_len = 4 + version.len() as u64;
+    let data_len: u64 = entries.iter().map(|entry| entry.size).sum();
+    let (data_start, total_size) = if layout.trailing_index {
+        (header_len, header_len + data_len + table_len + FOOTER_LEN)
+    } else {
+        (header_len + table_len, header_len + table_len + data_len)
+    };
+    assign_offsets(&mut entries, data_start, None)?;
+    let layout = PackLayout {
+        version: version.to_string(),
+        entries,
+        total_size,
+    };
+    Ok((files, layout))
+}
+
+/// 将文件夹重新打包为MPKG文件，得到的包解包后文件内容与文件夹相同
+///
+/// 按 `DEFAULT_VERSION` 写入，文件按路径排序，未知字段写入0，因此不会还原原包的格式版本、
+/// 文件顺序与未知字段；需要打包为与原包逐字节相同的文件时使用 `pack_mpkg_with_table`。
+pub fn pack_mpkg<P: AsRef<Path>>(input_dir: P, output_file: P) -> Result<(), MpkgError> {
+    pack_mpkg_with_table(input_dir, output_file, DEFAULT_VERSION, &[])
+}
+
+/// 将文件夹按 `version` 对应的格式重新打包，并沿用 `table` 中记录的文件顺序、未知字段与修改时间
+///
+/// `table` 通常是原包的 `list_mpkg` 结果，与 `read_header_version` 读出的版本一起传入时，
+/// 解包后未经修改的文件夹会被打包为与原包逐字节相同的文件。文件大小总是以磁盘上的文件为准；
+/// `table` 中不存在于打包目录的文件被忽略，未出现在 `table` 中的文件按路径排序接在后面，
+/// 未知字段写入0，修改时间取自文件本身。
+pub fn pack_mpkg_with_table<P: AsRef<Path>>(
+    input_dir: P,
+    output_file: P,
+    version: &str,
+    table: &[MpkgEntry],
+) -> Result<(), MpkgError> {
+    let output_file = output_file.as_ref();
+    let (files, plan) = plan_pack(input_dir.as_ref(), output_file, version, table)?;
+
+    write_archive_with(
+        File::create(output_file)?,
+        &plan.version,
+        &plan.entries,
+        |output_stream| {
+            // 依次写入文件数据
+            let mut written = 0;
+            for (path, entry) in files.iter().zip(&plan.entries) {
+                let mut input_stream = BufReader::new(File::open(path)?);
+                written += copy_stream_data(
+                    &mut input_stream,
+                    output_stream,
+                    0,
+                    entry.size,
+                    DEFAULT_BUFFER_SIZE,
+                    None,
+                    |_| {},
+                )?;
+            }
+            Ok(written)
+        },
+    )
+}
+
+/// 按与 `pack_mpkg` 相同的方式遍历打包目录，只返回将要写出的布局，不写入任何数据
+///
+/// 可以在打包大量文件之前检查文件列表并估算所需的磁盘空间。
+pub fn pack_mpkg_dry_run<P: AsRef<Path>>(
+    input_dir: P,
+    output_file: P,
+) -> Result<PackLayout, MpkgError> {
+    let (_, layout) = plan_pack(
+        input_dir.as_ref(),
+        output_file.as_ref(),
+        DEFAULT_VERSION,
+        &[],
+    )?;
+    Ok(layout)
+}
+
+/// 当前时间（Unix秒），作为没有修改时间的文件写入文件表的时间
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs())
+}
+
+/// 按 `layout` 写入文件数量与文件表，没有记录修改时间的文件写入0
+fn write_table<W: Write>(
+    writer: &mut W,
+    entries: &[MpkgEntry],
+    layout: Layout,
+) -> Result<(), MpkgError> {
+    let file_count = u32::try_from(entries.len())
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "文件数量过多"))?;
+    write_int32(writer, file_count)?;
+    for entry in entries {
+        write_string(writer, &entry.name)?;
+        write_int32(writer, entry.extra)?;
+        if layout.wide_sizes {
+            writer.write_all(&entry.size.to_le_bytes())?;
+        } else {
+            let size = u32::try_from(entry.size).map_err(|_| {
+                io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("文件超过4GB，需要使用8字节大小的格式版本：{}", entry.name),
+                )
+            })?;
+            write_int32(writer, size)?;
+        }
+        if layout.timestamps {
+            writer.write_all(&entry.modified.unwrap_or(0).to_le_bytes())?;
+        }
+    }
+    Ok(())
+}
+
+/// 文件表中一个文件占用的字节数
+fn table_entry_len(entry: &MpkgEntry, layout: Layout) -> u64 {
+    let size_len = if layout.wide_sizes { 8 } else { 4 };
+    let modified_len = if layout.timestamps { 8 } else { 0 };
+    4 + entry.name.len() as u64 + 4 + size_len + modified_len
+}
+
+/// 依次把每个文件的数据从对应的来源复制到 `writer`，来源提前结束时返回 `MpkgError::DataTruncated`
+fn write_data<W, R, I>(writer: &mut W, entries: &[MpkgEntry], readers: I) -> Result<u64, MpkgError>
+where
+    W: Write,
+    R: Read,
+    I: IntoIterator<Item = R>,
+{
+    let mut written = 0;
+    for (entry, mut reader) in entries.iter().zip(readers) {
+        written += copy_stream_data(
+            &mut reader,
+            writer,
+            entry.offset,
+            entry.size,
+            DEFAULT_BUFFER_SIZE,
+            None,
+            |_| {},
+        )?;
+    }
+    Ok(written)
+}
+
+/// 按 `version` 对应的格式写出完整的MPKG文件，`readers` 与 `entries` 一一对应
+fn write_archive<W, R, I>(
+    out: W,
+    version: &str,
+    entries: &[MpkgEntry],
+    readers: I,
+) -> Result<(), MpkgError>
+where
+    W: Write,
+    R: Read,
+    I: IntoIterator<Item = R>,
+{
+    write_archive_with(out, version, entries, |output_stream| {
+        write_data(output_stream, entries, readers)
+    })
+}
+
+/// 与 `write_archive` 相同，数据由 `write_data` 写出并返回写入的字节数
+fn write_archive_with<W, F>(
+    out: W,
+    version: &str,
+    entries: &[MpkgEntry],
+    write_data: F,
+) -> Result<(), MpkgError>
+where
+    W: Write,
+    F: FnOnce(&mut BufWriter<W>) -> Result<u64, MpkgError>,
+{
+    check_signature(version.as_bytes())?;
+    let layout = Layout::for_version(version);
+
+    let mut output_stream = BufWriter::new(out);
+    write_string(&mut output_stream, version)?;
+    if layout.trailing_index {
+        // 文件表位于数据之后，文件末尾记录文件表的起始偏移
+        let data_start = 4 + version.len() as u64;
+        let table_start = data_start + write_data(&mut output_stream)?;
+        write_table(&mut output_stream, entries, layout)?;
+        output_stream.write_all(&table_start.to_le_bytes())?;
+    } else {
+        write_table(&mut output_stream, entries, layout)?;
+        write_data(&mut output_stream)?;
+    }
+    output_stream.flush()?;
+    Ok(())
+}
+
+/// 由内存或其他来源的数据流生成MPKG文件，不需要读写文件系统
+///
+/// `version` 决定写入的格式，必须以 `PKGV` 或 `MPKG` 开头；记录修改时间的版本统一写入当前时间。
+/// 文件表需要提前写出每个文件的大小，因此只收集各个数据流，数据按 `size` 逐段复制，
+/// 不会整个读入内存。数据流超出 `size` 的部分被忽略。
+pub fn pack_mpkg_from_entries<W, I>(out: W, version: &str, entries: I) -> Result<(), MpkgError>
+where
+    W: Write,
+    I: IntoIterator<Item = (String, Box<dyn Read>, u64)>,
+{
+    let modified = now_secs();
+    let (table, readers): (Vec<_>, Vec<_>) = entries
+        .into_iter()
+        .enumerate()
+        .map(|(index, (name, reader, size))| {
+            let entry = MpkgEntry {
+                index,
+                name,
+                size,
+                offset: 0,
+                name_offset: 0,
+                extra: 0,
+                modified: Some(modified),
+            };
+            (entry, reader)
+        })
+        .unzip();
+    write_archive(out, version, &table, readers)
+}
+
+/// 将一个MPKG文件按大小拆分为多个MPKG文件，每个文件都不超过 `max_bytes` 字节
+///
+/// 输出文件名为 `output_prefix` 后接三位序号，例如前缀 `out/part_` 得到 `out/part_000.mpkg`、
+/// `out/part_001.mpkg` 等。文件按原顺序分配，同一个文件不会被拆到两个分卷中；
+/// 各分卷沿用原来的格式版本，文件的未知字段与修改时间保持不变，数据按原样复制。
+/// 单个文件加上头部与文件表后仍超过 `max_bytes` 时返回 `MpkgError::EntryTooLarge`，
+/// 此时不会写出任何分卷。返回按序号排列的分卷路径，空包也会写出一个不含文件的分卷。
+pub fn split_mpkg<P: AsRef<Path>>(
+    input: P,
+    output_prefix: P,
+    max_bytes: u64,
+) -> Result<Vec<PathBuf>, MpkgError> {
+    let archive = MpkgArchive::open(input)?;
+    archive.check_complete()?;
+    let parts = plan_parts(archive.version(), archive.entry_list(), max_bytes)?;
+
+    let mut readers = archive.entries().map(|(_, reader)| reader);
+    let mut paths = Vec::with_capacity(parts.len());
+    for (i, part) in parts.into_iter().enumerate() {
+        let mut path = output_prefix.as_ref().as_os_str().to_owned();
+        path.push(format!("{:03}.mpkg", i));
+        let path = PathBuf::from(path);
+
+        let entries = &archive.entry_list()[part.clone()];
+        let part_readers = readers.by_ref().take(part.len());
+        write_archive(
+            File::create(&path)?,
+            archive.version(),
+            entries,
+            part_readers,
+        )?;
+        paths.push(path);
+    }
+    Ok(paths)
+}
+
+/// 将多个MPKG文件合并为一个，按 `version` 对应的格式写入 `output`
+///
+/// 文件按输入顺序排列，未知字段与修改时间保持不变，数据按原样复制；
+/// 没有记录修改时间的文件在带时间的格式中写入当前时间。不同输入中的同名文件按 `duplicates` 处理：
+/// `LastWins` 只保留最后一个输入中的文件，`FirstWins` 只保留最先出现的输入中的文件，
+/// `Error` 返回 `MpkgError::DuplicateEntry` 并给出两个文件所在的包，
+/// `Rename` 把后出现的文件放到以其所在包名命名的目录下，例如 `b.mpkg` 中的 `a.png` 写为 `b/a.png`。
+/// 同一个输入内部的同名文件原样保留。
+pub fn merge_mpkg<P: AsRef<Path>>(
+    inputs: &[PathBuf],
+    output: P,
+    version: &str,
+    duplicates: DuplicatePolicy,
+) -> Result<(), MpkgError> {
+    let output = output.as_ref();
+    check_signature(version.as_bytes())?;
+
+    // 写入输出文件会截断它，因此不能同时作为输入
+    if let Ok(output_path) = fs::canonicalize(output) {
+        if inputs
+            .iter()
+            .any(|input| fs::canonicalize(input).is_ok_and(|path| path == output_path))
+        {
+            return Err(MpkgError::InvalidOptions(format!(
+                "输出文件同时也是输入文件：{}",
+                output.display()
+            )));
+        }
+    }
+
+    let archives = inputs
+        .iter()
+        .map(|input| {
+            let archive = MpkgArchive::open(input)?;
+            archive.check_complete()?;
+            Ok(archive)
+        })
+        .collect::<Result<Vec<_>, MpkgError>>()?;
+
+    // 每个位置记录来源的包与文件表项，被后出现的同名文件取代时置为 `None`
+    let mut merged: Vec<Option<(usize, MpkgEntry)>> = Vec::new();
+    // 每个文件名对应的全部位置及它们所在的包，同一个包内的同名文件都记在这里
+    let mut owners: HashMap<String, (Vec<usize>, usize)> = HashMap::new();
+    for (source, archive) in archives.iter().enumerate() {
+        for entry in archive.entry_list() {
+            let mut entry = entry.clone();
+            match owners.get(&entry.name) {
+                Some((positions, owner_source)) if *owner_source != source => match duplicates {
+                    DuplicatePolicy::LastWins => {
+                        for &position in positions {
+                            merged[position] = None;
+                        }
+                        owners.remove(&entry.name);
+                    }
+                    DuplicatePolicy::FirstWins => continue,
+                    DuplicatePolicy::Error => {
+                        let first = merged[positions[0]]
+                            .as_ref()
+                            .map_or(0, |(_, first)| first.offset);
+                        return Err(MpkgError::DuplicateEntry {
+                            name: entry.name,
+                            first_offset: first,
+                            second_offset: entry.offset,
+                            first_archive: Some(inputs[*owner_source].clone()),
+                            second_archive: Some(inputs[source].clone()),
+                        });
+                    }
+                    DuplicatePolicy::Rename => {
+                        let stem = archive_folder_name(&inputs[source])?.to_string_lossy();
+                        let mut name = format!("{}/{}", stem, entry.name);
+                        let mut n = 1;
+                        while owners.contains_key(&name) {
+                            name = format!("{}_{}/{}", stem, n, entry.name);
+                            n += 1;
+                        }
+                        entry.name = name;
+                    }
+                },
+                _ => {}
+            }
+            owners
+                .entry(entry.name.clone())
+                .or_insert_with(|| (Vec::new(), source))
+                .0
+                .push(merged.len());
+            merged.push(Some((source, entry)));
+        }
+    }
+
+    let (sources, mut entries): (Vec<_>, Vec<_>) = merged.into_iter().flatten().unzip();
+    let modified = now_secs();
+    for (index, entry) in entries.iter_mut().enumerate() {
+        entry.index = index;
+        entry.modified.get_or_insert(modified);
+    }
+    let readers = sources
+        .iter()
+        .zip(&entries)
+        .map(|(&source, entry)| archives[source].reader(entry));
+    write_archive(File::create(output)?, version, &entries, readers)
+}
+
+/// 按顺序把文件分配到各个分卷，返回每个分卷包含的文件范围
+fn plan_parts(
+    version: &str,
+    entries: &[MpkgEntry],
+    max_bytes: u64,
+) -> Result<Vec<Range<usize>>, MpkgError> {
+    let layout = Layout::for_version(version);
+    let footer_len = if layout.trailing_index { FOOTER_LEN } else { 0 };
+    // 头部、文件数量与末尾偏移是每个分卷都有的固定开销
+    let empty_len = 4 + version.len() as u64 + 4 + footer_len;
+
+    let mut parts = Vec::new();
+    let mut start = 0;
+    let mut part_len = empty_len;
+    for (i, entry) in entries.iter().enumerate() {
+        let entry_len = table_entry_len(entry, layout).saturating_add(entry.size);
+        if empty_len.saturating_add(entry_len) > max_bytes {
+            return Err(MpkgError::EntryTooLarge {
+                name: entry.name.clone(),
+                size: empty_len.saturating_add(entry_len),
+                limit: max_bytes,
+            });
+        }
+        if part_len + entry_len > max_bytes {
+            parts.push(start..i);
+            start = i;
+            part_len = empty_len;
+        }
+        part_len += entry_len;
+    }
+    parts.push(start..entries.len());
+    Ok(parts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{quiet, ArchiveBuilder};
+    use crate::{list_mpkg, read_header_version, unpack_with_options};
+
+    #[test]
+    fn round_trip_keeps_table() {
+        for version in ["PKGV0001", "MPKG0003", "MPKG0004"] {
+            // 文件表顺序不是路径顺序，未知字段各不相同
+            let original = ArchiveBuilder::new(version)
+                .entry(b"b.txt", 0x1234_5677, 3, b"bbb")
+                .entry(b"a/c.bin", 7, 4, &[0, 1, 2, 3])
+                .entry(b"a.txt", 0, 0, b"")
+                .build();
+            let dir = tempfile::tempdir().unwrap();
+            let input = dir.path().join("a.mpkg");
+            fs::write(&input, &original).unwrap();
+            let out = dir.path().join("out");
+            unpack_with_options(&input, &out, &quiet()).unwrap();
+
+            let repacked = dir.path().join("b.mpkg");
+            let table = list_mpkg(&input).unwrap();
+            let header = read_header_version(&mut File::open(&input).unwrap()).unwrap();
+            pack_mpkg_with_table(&out.join("a"), &repacked, &header, &table).unwrap();
+            assert_eq!(fs::read(&repacked).unwrap(), original, "{}", version);
+
+            let extras: Vec<_> = list_mpkg(&repacked)
+                .unwrap()
+                .into_iter()
+                .map(|entry| (entry.name, entry.extra))
+                .collect();
+            assert_eq!(
+                extras,
+                [
+                    ("b.txt".to_string(), 0x1234_5677),
+                    ("a/c.bin".to_string(), 7),
+                    ("a.txt".to_string(), 0),
+                ]
+            );
+        }
+    }
+
+    #[test]
+    fn merge_last_wins_drops_duplicates_inside_one_input() {
+        let dir = tempfile::tempdir().unwrap();
+        let first = dir.path().join("a.mpkg");
+        let second = dir.path().join("b.mpkg");
+        // 第一个包内部就有两个 `x.txt`
+        let archive = ArchiveBuilder::new("PKGV0001")
+            .file("x.txt", b"one")
+            .file("y.txt", b"y")
+            .file("x.txt", b"two")
+            .build();
+        fs::write(&first, archive).unwrap();
+        fs::write(
+            &second,
+            ArchiveBuilder::new("PKGV0001")
+                .file("x.txt", b"three")
+                .build(),
+        )
+        .unwrap();
+        let inputs = [first.clone(), second.clone()];
+
+        let merged = dir.path().join("merged.mpkg");
+        merge_mpkg(&inputs, &merged, "PKGV0001", DuplicatePolicy::LastWins).unwrap();
+        let archive = MpkgArchive::open(&merged).unwrap();
+        let names: Vec<_> = archive
+            .entry_list()
+            .iter()
+            .map(|entry| entry.name.as_str())
+            .collect();
+        assert_eq!(names, ["y.txt", "x.txt"]);
+        let mut data = Vec::new();
+        archive
+            .reader(&archive.entry_list()[1])
+            .read_to_end(&mut data)
+            .unwrap();
+        assert_eq!(data, b"three");
+
+        match merge_mpkg(&inputs, &merged, "PKGV0001", DuplicatePolicy::Error) {
+            Err(MpkgError::DuplicateEntry {
+                name,
+                first_archive,
+                second_archive,
+                ..
+            }) => {
+                assert_eq!(name, "x.txt");
+                assert_eq!(first_archive, Some(first));
+                assert_eq!(second_archive, Some(second));
+            }
+            other => panic!("{:?}", other),
+        }
+    }
+}
@@ -0,0 +1,625 @@
+//! 头部与文件表的解析，只依赖 `std::io` 的 `Read` 与 `Seek`，不访问文件系统也不处理路径；
+//! 打开与写出文件的逻辑都在其他模块中。
+
+use std::io::{self, Read, Seek, SeekFrom};
+
+use crate::format::{check_signature, Layout, COMPRESSED_FLAG, FOOTER_LEN};
+use crate::{FilenameEncoding, MpkgError, UnpackOptions};
+
+/// 读取文件表时最多预先分配的项数
+const PREALLOCATED_ENTRIES: usize = 4096;
+
+/// 读取4字节的整数 (小端序)
+pub(crate) fn read_int32<R: Read>(reader: &mut R) -> io::Result<u32> {
+    let mut buffer = [0u8; 4];
+    reader.read_exact(&mut buffer)?;
+    Ok(u32::from_le_bytes(buffer))
+}
+
+/// 读取8字节的整数 (小端序)
+pub(crate) fn read_int64<R: Read>(reader: &mut R) -> io::Result<u64> {
+    let mut buffer = [0u8; 8];
+    reader.read_exact(&mut buffer)?;
+    Ok(u64::from_le_bytes(buffer))
+}
+
+/// 获取流的总长度，并保持当前位置不变
+pub(crate) fn stream_len<R: Seek>(reader: &mut R) -> io::Result<u64> {
+    let position = reader.stream_position()?;
+    let len = reader.seek(SeekFrom::End(0))?;
+    reader.seek(SeekFrom::Start(position))?;
+    Ok(len)
+}
+
+/// 读取文件表时遇到EOF说明文件表不完整，`offset` 为这次读取开始的位置
+fn table_error(offset: u64) -> impl FnOnce(io::Error) -> MpkgError {
+    move |e| {
+        if e.kind() == io::ErrorKind::UnexpectedEof {
+            MpkgError::TruncatedEntryTable { offset }
+        } else {
+            MpkgError::Io(e)
+        }
+    }
+}
+
+/// MPKG文件表中的一项
+///
+/// 所有返回文件表的函数都按文件表中的原始顺序给出各项，`index` 与其位置一致。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MpkgEntry {
+    /// 在文件表中的序号，从0开始
+    pub index: usize,
+    /// 包内文件名（相对路径）
+    pub name: String,
+    /// 文件大小（字节）
+    pub size: u64,
+    /// 文件数据在包内的起始偏移
+    pub offset: u64,
+    /// 文件名在文件表中的起始偏移，用于报告错误；不是从文件读取的表项为0
+    pub name_offset: u64,
+    /// 文件名与大小之间的未知字段 (4字节)
+    pub extra: u32,
+    /// 文件修改时间（Unix秒），只有新版本格式记录该字段
+    pub modified: Option<u64>,
+}
+
+impl MpkgEntry {
+    /// 未知字段是否标记该文件的数据块为deflate压缩
+    ///
+    /// 只有启用 `UnpackOptions::decompress` 时解包才会按此标记解压。
+    pub fn is_compressed(&self) -> bool {
+        self.extra & COMPRESSED_FLAG != 0
+    }
+}
+
+/// 按指定编码解码从 `offset` 处读取的字节
+pub(crate) fn decode_name(
+    bytes: &[u8],
+    offset: u64,
+    encoding: &FilenameEncoding,
+) -> Result<String, MpkgError> {
+    encoding
+        .decode(bytes)
+        .map_err(|invalid_at| MpkgError::InvalidEncoding {
+            offset: offset + invalid_at as u64,
+        })
+}
+
+/// 头部的格式版本字符串及其末尾的填充
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HeaderInfo {
+    /// 去掉末尾空字节后解码得到的格式版本字符串
+    pub version: String,
+    /// 头部末尾用于填充的空字节数，没有填充时为0
+    pub padding: usize,
+}
+
+/// 读取头部的格式版本字符串，`archive_len` 未知时只按 `max_header_length` 限制头部长度
+pub(crate) fn read_header<R: Read>(
+    reader: &mut R,
+    archive_len: Option<u64>,
+    options: &UnpackOptions,
+) -> Result<String, MpkgError> {
+    parse_header(reader, archive_len, options).map(|header| header.version)
+}
+
+/// 读取并解码头部，末尾的空字节不参与解码
+///
+/// 解码使用 `header_encoding`，未设置时使用 `encoding`。
+fn parse_header<R: Read>(
+    reader: &mut R,
+    archive_len: Option<u64>,
+    options: &UnpackOptions,
+) -> Result<HeaderInfo, MpkgError> {
+    let header_length = match read_int32(reader) {
+        Ok(length) => length,
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => {
+            return Err(MpkgError::BadHeader {
+                detail: match archive_len {
+                    Some(archive_len) => format!("文件过短：{} 字节", archive_len),
+                    None => "文件过短".to_string(),
+                },
+                offset: 0,
+            });
+        }
+        Err(e) => return Err(e.into()),
+    };
+    if header_length as u64 > options.max_header_length as u64 {
+        return Err(MpkgError::BadHeader {
+            detail: format!(
+                "头部长度 {} 超出上限 {}",
+                header_length, options.max_header_length
+            ),
+            offset: 0,
+        });
+    }
+    if let Some(archive_len) = archive_len {
+        if header_length as u64 > archive_len.saturating_sub(4) {
+            return Err(MpkgError::BadHeader {
+                detail: format!("头部长度 {} 超出文件大小 {}", header_length, archive_len),
+                offset: 0,
+            });
+        }
+    }
+
+    let mut header_bytes = vec![0u8; header_length as usize];
+    reader.read_exact(&mut header_bytes).map_err(|e| {
+        if e.kind() == io::ErrorKind::UnexpectedEof {
+            MpkgError::BadHeader {
+                detail: format!("头部长度 {} 超出文件大小", header_length),
+                offset: 0,
+            }
+        } else {
+            MpkgError::Io(e)
+        }
+    })?;
+    check_signature(&header_bytes)?;
+
+    let text_len = header_bytes
+        .iter()
+        .rposition(|&byte| byte != 0)
+        .map_or(0, |last| last + 1);
+    let encoding = options
+        .header_encoding
+        .as_ref()
+        .unwrap_or(&options.encoding);
+    Ok(HeaderInfo {
+        version: decode_name(&header_bytes[..text_len], 4, encoding)?,
+        padding: header_bytes.len() - text_len,
+    })
+}
+
+/// 只从流的当前位置读取头部的格式版本字符串，用于快速判断文件格式
+///
+/// 末尾的空字节会被去掉。不读取文件表，读取后流位于文件数量字段之前。
+pub fn read_header_version<R: Read>(reader: &mut R) -> Result<String, MpkgError> {
+    read_header(reader, None, &UnpackOptions::default())
+}
+
+/// 与 `read_header_version` 相同，同时给出头部末尾被去掉的空字节数
+pub fn read_header_info<R: Read>(
+    reader: &mut R,
+    options: &UnpackOptions,
+) -> Result<HeaderInfo, MpkgError> {
+    parse_header(reader, None, options)
+}
+
+/// 从流的当前位置读取文件数量与文件表，`table_limit` 为文件表最多可以延伸到的位置
+///
+/// 返回的各文件偏移尚未确定，由 `assign_offsets` 填写。
+pub(crate) fn read_entry_table<R: Read + Seek>(
+    reader: &mut R,
+    table_limit: u64,
+    layout: Layout,
+    options: &UnpackOptions,
+) -> Result<Vec<MpkgEntry>, MpkgError> {
+    // `position` 始终是下一个字段的起始位置，出错时报告
+    let mut position = reader.stream_position()?;
+    let file_count = read_int32(reader).map_err(table_error(position))?;
+    if file_count as u64 > options.max_file_count as u64 {
+        return Err(MpkgError::BadHeader {
+            detail: format!(
+                "文件数量 {} 超出上限 {}",
+                file_count, options.max_file_count
+            ),
+            offset: position,
+        });
+    }
+    position += 4;
+
+    // 文件数量来自未经验证的文件，只预留有限的容量，其余随读取增长
+    let mut entries = Vec::with_capacity((file_count as usize).min(PREALLOCATED_ENTRIES));
+    for index in 0..file_count as usize {
+        let entry_start = position;
+        let name_length = read_int32(reader).map_err(table_error(position))? as u64;
+        position += 4;
+        if name_length > options.max_name_length as u64
+            || name_length > table_limit.saturating_sub(position)
+        {
+            return Err(MpkgError::NameTooLong {
+                length: name_length,
+                offset: entry_start,
+            });
+        }
+        let mut name_bytes = vec![0u8; name_length as usize];
+        reader
+            .read_exact(&mut name_bytes)
+            .map_err(table_error(position))?;
+        let name_offset = position;
+        let name = decode_name(&name_bytes, name_offset, &options.encoding)?;
+        position += name_length;
+
+        // 未知字段 (4字节)
+        let extra = read_int32(reader).map_err(table_error(position))?;
+        position += 4;
+
+        // 读取文件大小，新版本格式使用8字节
+        let size = if layout.wide_sizes {
+            let size = read_int64(reader).map_err(table_error(position))?;
+            position += 8;
+            size
+        } else {
+            let size = read_int32(reader).map_err(table_error(position))? as u64;
+            position += 4;
+            size
+        };
+
+        // 新版本格式在大小之后记录修改时间
+        let modified = if layout.timestamps {
+            let modified = read_int64(reader).map_err(table_error(position))?;
+            position += 8;
+            Some(modified)
+        } else {
+            None
+        };
+        entries.push(MpkgEntry {
+            index,
+            name,
+            size,
+            offset: 0,
+            name_offset,
+            extra,
+            modified,
+        });
+    }
+
+    Ok(entries)
+}
+
+/// 文件数据从 `data_start` 开始按文件表顺序依次存放，根据文件大小推算每个文件的数据偏移
+///
+/// 文件表在后时数据必须在 `table_start` 之前结束，否则数据区域与文件表重叠。
+/// 文件表在前时数据超出文件末尾通常是下载不完整，留给 `check_complete` 报告。
+pub(crate) fn assign_offsets(
+    entries: &mut [MpkgEntry],
+    data_start: u64,
+    table_start: Option<u64>,
+) -> Result<(), MpkgError> {
+    let mut offset = data_start;
+    for entry in entries {
+        entry.offset = offset;
+        let corrupt = |detail: String| MpkgError::CorruptTable {
+            entry: entry.name.clone(),
+            detail,
+            offset,
+        };
+        let end = offset.checked_add(entry.size).ok_or_else(|| {
+            corrupt(format!(
+                "从偏移 {} 起的 {} 字节超出64位偏移范围",
+                offset, entry.size
+            ))
+        })?;
+        if let Some(table_start) = table_start.filter(|&table_start| end > table_start) {
+            return Err(corrupt(format!(
+                "数据区域 {}..{} 与从偏移 {} 开始的文件表重叠",
+                offset, end, table_start
+            )));
+        }
+        offset = end;
+    }
+    Ok(())
+}
+
+/// 确定文件表的起始位置：文件表在前时紧跟在头部之后，在后时由文件末尾的偏移记录
+fn locate_table<R: Read + Seek>(
+    reader: &mut R,
+    archive_len: u64,
+    header_end: u64,
+    layout: Layout,
+) -> Result<u64, MpkgError> {
+    if !layout.trailing_index {
+        return Ok(header_end);
+    }
+    let footer_start = archive_len
+        .checked_sub(FOOTER_LEN)
+        .filter(|&footer_start| footer_start >= header_end)
+        .ok_or(MpkgError::TruncatedEntryTable { offset: header_end })?;
+    reader.seek(SeekFrom::Start(footer_start))?;
+    let table_start = read_int64(reader).map_err(table_error(footer_start))?;
+    if table_start < header_end || table_start > footer_start {
+        return Err(MpkgError::BadHeader {
+            detail: format!(
+                "文件表偏移 {} 超出数据范围 {}..{}",
+                table_start, header_end, footer_start
+            ),
+            offset: footer_start,
+        });
+    }
+    Ok(table_start)
+}
+
+/// 已读取的头部与文件表
+pub(crate) struct ArchiveTable {
+    pub(crate) version: String,
+    pub(crate) entries: Vec<MpkgEntry>,
+    /// 整个文件的长度
+    pub(crate) archive_len: u64,
+    /// 第一个文件数据的起始位置
+    pub(crate) data_start: u64,
+    /// 文件数据最多可以延伸到的位置：文件表在前时为文件末尾，在后时为文件表的起始位置
+    pub(crate) data_limit: u64,
+}
+
+impl ArchiveTable {
+    /// 最后一个文件数据的结束位置，即文件表所描述的完整文件长度
+    pub(crate) fn data_end(&self) -> u64 {
+        self.entries.last().map_or(self.data_start, |entry| {
+            entry.offset.saturating_add(entry.size)
+        })
+    }
+
+    /// 检查文件长度是否足以容纳文件表所描述的全部数据，尽早发现下载不完整的文件
+    pub(crate) fn check_complete(&self) -> Result<(), MpkgError> {
+        let data_end = self.data_end();
+        if data_end > self.data_limit {
+            return Err(MpkgError::DataTruncated {
+                expected: data_end,
+                got: self.data_limit,
+                offset: self.data_limit,
+            });
+        }
+        Ok(())
+    }
+}
+
+/// 从流的当前位置读取头部与文件表
+pub(crate) fn read_archive_table<R: Read + Seek>(
+    reader: &mut R,
+    options: &UnpackOptions,
+) -> Result<ArchiveTable, MpkgError> {
+    let archive_len = stream_len(reader)?;
+    parse_archive_table(reader, archive_len, options)
+}
+
+/// 从只能向前读取的流中读取头部与文件表，不定位到流的末尾，流停在第一个文件数据的起始处
+///
+/// 整个文件的长度未知，文件表位于末尾的格式无法读取；数据是否完整留到复制数据时检查。
+#[cfg(feature = "compression")]
+pub(crate) fn read_leading_table<R: Read + Seek>(
+    reader: &mut R,
+    options: &UnpackOptions,
+) -> Result<ArchiveTable, MpkgError> {
+    let version = read_header(reader, None, options)?;
+    let layout = Layout::for_version(&version);
+    if layout.trailing_index {
+        return Err(MpkgError::BadHeader {
+            detail: format!("格式版本 {} 的文件表位于文件末尾，无法顺序读取", version),
+            offset: 4,
+        });
+    }
+    let mut entries = read_entry_table(reader, u64::MAX, layout, options)?;
+    let data_start = reader.stream_position()?;
+    assign_offsets(&mut entries, data_start, None)?;
+    Ok(ArchiveTable {
+        version,
+        entries,
+        archive_len: u64::MAX,
+        data_start,
+        data_limit: u64::MAX,
+    })
+}
+
+/// 从流的当前位置读取头部与文件表，`archive_len` 为整个文件的长度
+///
+/// `reader` 可以只包含文件开头的一部分，文件表读不完整时返回 `MpkgError::TruncatedEntryTable`。
+/// 文件表位于末尾的格式会先定位到文件表，读取后流停在文件表末尾。
+pub(crate) fn parse_archive_table<R: Read + Seek>(
+    reader: &mut R,
+    archive_len: u64,
+    options: &UnpackOptions,
+) -> Result<ArchiveTable, MpkgError> {
+    let version = read_header(reader, Some(archive_len), options)?;
+    let layout = Layout::for_version(&version);
+    let header_end = reader.stream_position()?;
+
+    let table_start = locate_table(reader, archive_len, header_end, layout)?;
+    let (table_limit, data_limit) = if layout.trailing_index {
+        reader.seek(SeekFrom::Start(table_start))?;
+        (archive_len - FOOTER_LEN, table_start)
+    } else {
+        (archive_len, archive_len)
+    };
+    let mut entries = read_entry_table(reader, table_limit, layout, options)?;
+
+    // 文件表在前时数据紧跟在文件表之后，在后时数据紧跟在头部之后
+    let data_start = if layout.trailing_index {
+        header_end
+    } else {
+        reader.stream_position()?
+    };
+    assign_offsets(
+        &mut entries,
+        data_start,
+        layout.trailing_index.then_some(table_start),
+    )?;
+    Ok(ArchiveTable {
+        version,
+        entries,
+        archive_len,
+        data_start,
+        data_limit,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{largest_allocation, quiet, unpack_bytes, ArchiveBuilder};
+
+    /// 头部为 `PKGV0001`、只有一个文件的文件表开头，文件名长度字段为 `name_length`
+    fn table_with_name_length(name_length: u32) -> Vec<u8> {
+        let mut bytes = ArchiveBuilder::new("PKGV0001").build();
+        // 把文件数量改为1，再追加文件名长度
+        bytes.truncate(bytes.len() - 4);
+        bytes.extend_from_slice(&1u32.to_le_bytes());
+        bytes.extend_from_slice(&name_length.to_le_bytes());
+        bytes
+    }
+
+    #[test]
+    fn oversized_name_length() {
+        // 长度超出文件剩余部分
+        let bytes = table_with_name_length(u32::MAX);
+        let dir = tempfile::tempdir().unwrap();
+        let (result, largest) = largest_allocation(|| unpack_bytes(&bytes, dir.path(), &quiet()));
+        assert!(
+            matches!(
+                result,
+                Err(MpkgError::NameTooLong {
+                    length: 0xffff_ffff,
+                    offset: 16
+                })
+            ),
+            "{:?}",
+            result
+        );
+        assert!(largest < 1024 * 1024, "分配了 {} 字节", largest);
+        assert!(!dir.path().join("out").exists());
+
+        // 长度位于文件之内，但超出 `max_name_length`
+        let mut bytes = table_with_name_length(100_000);
+        bytes.resize(bytes.len() + 200_000, b'a');
+        let options = UnpackOptions {
+            max_name_length: 64 * 1024,
+            ..quiet()
+        };
+        let (result, largest) = largest_allocation(|| unpack_bytes(&bytes, dir.path(), &options));
+        assert!(
+            matches!(
+                result,
+                Err(MpkgError::NameTooLong {
+                    length: 100_000,
+                    ..
+                })
+            ),
+            "{:?}",
+            result
+        );
+        assert!(largest < 100_000, "分配了 {} 字节", largest);
+        assert!(!dir.path().join("out").exists());
+    }
+
+    /// 头部为 `PKGV0001`、文件数量为 `file_count`、之后没有任何文件表项的文件
+    fn table_with_file_count(file_count: u32) -> Vec<u8> {
+        let mut bytes = ArchiveBuilder::new("PKGV0001").build();
+        bytes.truncate(bytes.len() - 4);
+        bytes.extend_from_slice(&file_count.to_le_bytes());
+        bytes
+    }
+
+    #[test]
+    fn huge_file_count() {
+        let dir = tempfile::tempdir().unwrap();
+        let bytes = table_with_file_count(u32::MAX);
+        let (result, largest) = largest_allocation(|| unpack_bytes(&bytes, dir.path(), &quiet()));
+        assert!(
+            matches!(result, Err(MpkgError::BadHeader { offset: 12, .. })),
+            "{:?}",
+            result
+        );
+        assert!(largest < 1024 * 1024, "分配了 {} 字节", largest);
+        assert!(!dir.path().join("out").exists());
+
+        // 低于上限但文件表不完整时，只预留有限的容量
+        let bytes = table_with_file_count(400_000);
+        let (result, largest) = largest_allocation(|| unpack_bytes(&bytes, dir.path(), &quiet()));
+        assert!(
+            matches!(result, Err(MpkgError::TruncatedEntryTable { offset: 16 })),
+            "{:?}",
+            result
+        );
+        assert!(
+            largest <= PREALLOCATED_ENTRIES * std::mem::size_of::<MpkgEntry>(),
+            "分配了 {} 字节",
+            largest
+        );
+        assert!(!dir.path().join("out").exists());
+    }
+
+    #[test]
+    fn offsets_increase_and_point_at_data() {
+        let files: [(&str, &[u8]); 4] = [
+            ("a.txt", b"alpha"),
+            ("empty", b""),
+            ("dir/b.bin", b"\x00\x01\x02"),
+            ("c", b"gamma!"),
+        ];
+        for version in ["PKGV0001", "MPKG0003", "MPKG0004"] {
+            let bytes = files
+                .iter()
+                .fold(ArchiveBuilder::new(version), |builder, (name, data)| {
+                    builder.file(name, data)
+                })
+                .build();
+            let dir = tempfile::tempdir().unwrap();
+            let path = dir.path().join("a.mpkg");
+            std::fs::write(&path, &bytes).unwrap();
+
+            let offsets = crate::entry_offsets(&path).unwrap();
+            assert_eq!(offsets.len(), files.len());
+            for (pair, expected) in offsets.windows(2).zip(&files) {
+                let (_, offset, size) = &pair[0];
+                let (_, next_offset, _) = &pair[1];
+                assert_eq!(offset + size, *next_offset, "{}: {}", version, expected.0);
+            }
+            for ((name, offset, size), (expected_name, data)) in offsets.iter().zip(&files) {
+                assert_eq!(name, expected_name);
+                let range = *offset as usize..(*offset + *size) as usize;
+                assert_eq!(&bytes[range], *data, "{}: {}", version, name);
+            }
+        }
+    }
+
+    #[test]
+    fn sizes_that_do_not_add_up() {
+        // 文件表在后时，数据区域延伸进文件表
+        let bytes = ArchiveBuilder::new("MPKG0004")
+            .file("a.txt", b"aaaa")
+            .entry(b"b.txt", 0, 100, b"bbbb")
+            .build();
+        let dir = tempfile::tempdir().unwrap();
+        let result = unpack_bytes(&bytes, dir.path(), &quiet());
+        assert!(
+            matches!(
+                &result,
+                Err(MpkgError::CorruptTable { entry, offset: 16, .. }) if entry == "b.txt"
+            ),
+            "{:?}",
+            result
+        );
+        assert!(!dir.path().join("out").exists());
+
+        // 偏移累加超出64位范围
+        let bytes = ArchiveBuilder::new("MPKG0002")
+            .entry(b"a.bin", 0, u64::MAX - 4, b"")
+            .entry(b"b.bin", 0, 16, b"")
+            .build();
+        let result = unpack_bytes(&bytes, dir.path(), &quiet());
+        assert!(
+            matches!(&result, Err(MpkgError::CorruptTable { .. })),
+            "{:?}",
+            result
+        );
+        assert!(!dir.path().join("out").exists());
+    }
+
+    #[cfg(feature = "shift-jis")]
+    #[test]
+    fn shift_jis_invalid_offset() {
+        // 文件名从偏移20开始，前三个字节 `あa` 有效，第四个字节无效
+        let bytes = ArchiveBuilder::new("PKGV0001")
+            .entry(b"\x82\xa0a\xff", 0, 1, b"x")
+            .build();
+        let dir = tempfile::tempdir().unwrap();
+        let options = UnpackOptions {
+            encoding: FilenameEncoding::ShiftJis,
+            ..quiet()
+        };
+        let error = unpack_bytes(&bytes, dir.path(), &options).unwrap_err();
+        assert!(
+            matches!(error, MpkgError::InvalidEncoding { offset: 23 }),
+            "{:?}",
+            error
+        );
+    }
+}
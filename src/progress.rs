@@ -0,0 +1,110 @@
+use std::path::PathBuf;
+
+use crate::{LogLevel, UnpackReport};
+
+/// 解包过程中的进度事件
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProgressEvent {
+    /// 已读取头部与文件表
+    ArchiveOpened {
+        version: String,
+        file_count: usize,
+        total_bytes: u64,
+    },
+    /// 开始解包第 `index` 个文件 (从0开始)
+    FileStarted {
+        name: String,
+        index: usize,
+        total: usize,
+        size: u64,
+        /// 文件数据在包内的起始偏移
+        offset: u64,
+    },
+    /// 第 `index` 个文件因选项被跳过
+    FileSkipped { name: String, index: usize },
+    /// 第 `index` 个文件解包失败，已跳过其剩余数据继续解包
+    FileFailed {
+        name: String,
+        index: usize,
+        error: String,
+    },
+    /// 当前文件已复制的字节数，以及整个包已复制的字节数
+    BytesCopied { file_bytes: u64, total_bytes: u64 },
+    /// 当前文件解包完成
+    FileFinished { name: String },
+    /// 整个包解包完成
+    Finished,
+}
+
+/// 解包文件夹中的多个MPKG文件时，每个包开始与结束的进度事件
+///
+/// 与每个包内部的 `ProgressEvent` 分开报告，便于同时显示整体与单个包的进度。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DirProgressEvent {
+    /// 开始解包第 `index` 个MPKG文件 (从0开始)，共 `total` 个
+    ArchiveStarted {
+        path: PathBuf,
+        index: usize,
+        total: usize,
+    },
+    /// 第 `index` 个MPKG文件处理结束，失败时给出错误信息
+    ArchiveFinished {
+        path: PathBuf,
+        index: usize,
+        result: Result<UnpackReport, String>,
+    },
+}
+
+/// 返回按 `level` 将进度事件打印到标准输出的回调
+///
+/// 各个打印进度的函数都使用它；自定义回调中也可以转发给它以保留默认的输出。
+/// 打印的信息只有中文，与命令行的用法说明一致；需要其他语言时在自定义回调中按 `ProgressEvent` 自行输出。
+pub fn print_progress(level: LogLevel) -> impl FnMut(ProgressEvent) {
+    move |event| {
+        // 出错信息在安静模式下同样输出
+        if level > LogLevel::Quiet || matches!(event, ProgressEvent::FileFailed { .. }) {
+            print_event(event, level == LogLevel::Verbose);
+        }
+    }
+}
+
+/// 打印一个进度事件，`verbose` 时附带偏移与大小
+fn print_event(event: ProgressEvent, verbose: bool) {
+    match event {
+        ProgressEvent::ArchiveOpened {
+            version,
+            file_count,
+            total_bytes,
+        } => {
+            println!("文件格式版本：{}", version);
+            println!("发现文件数量：{}", file_count);
+            if verbose {
+                println!("待解包数据：{} 字节", total_bytes);
+            }
+        }
+        ProgressEvent::FileStarted {
+            name,
+            index,
+            total,
+            size,
+            offset,
+        } if verbose => println!(
+            "正在解包文件 {}/{} : {} (偏移 {}，大小 {} 字节)",
+            index + 1,
+            total,
+            name,
+            offset,
+            size
+        ),
+        ProgressEvent::FileStarted {
+            name, index, total, ..
+        } => println!("正在解包文件 {}/{} : {}", index + 1, total, name),
+        ProgressEvent::FileSkipped { name, .. } => println!("跳过文件: {}", name),
+        ProgressEvent::FileFailed { name, error, .. } => {
+            eprintln!("文件解包失败: {}: {}", name, error)
+        }
+        ProgressEvent::BytesCopied { .. } => {}
+        ProgressEvent::FileFinished { name } => println!("文件解包完成: {}", name),
+        ProgressEvent::Finished => println!("解包成功完成！"),
+    }
+}
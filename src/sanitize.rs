@@ -0,0 +1,243 @@
+use std::borrow::Cow;
+use std::path::{Component, Path, PathBuf};
+
+use crate::{MpkgError, ReservedNamePolicy, UnpackOptions};
+
+/// Windows保留的设备名，不区分大小写，带扩展名时同样保留
+const RESERVED_NAMES: &[&str] = &[
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// 判断字符是否不能出现在Windows文件名中
+fn is_illegal_char(c: char) -> bool {
+    c < ' ' || matches!(c, '<' | '>' | ':' | '"' | '/' | '\\' | '|' | '?' | '*')
+}
+
+/// 判断一段路径在Windows上是否不可用：保留设备名、非法字符或以点、空格结尾
+fn is_reserved_component(part: &str) -> bool {
+    let stem = part.split('.').next().unwrap_or_default();
+    RESERVED_NAMES
+        .iter()
+        .any(|reserved| stem.eq_ignore_ascii_case(reserved))
+        || part.chars().any(is_illegal_char)
+        || part.ends_with(['.', ' '])
+}
+
+/// 将一段路径改写为Windows上可用的名称
+///
+/// 非法字符替换为 `_`，去掉结尾的点与空格，保留设备名前追加 `_`，例如 `CON.txt` -> `_CON.txt`。
+fn rewrite_component(part: &str) -> String {
+    let replaced: String = part
+        .chars()
+        .map(|c| if is_illegal_char(c) { '_' } else { c })
+        .collect();
+    let trimmed = replaced.trim_end_matches(['.', ' ']);
+    if trimmed.is_empty() {
+        return "_".to_string();
+    }
+    let stem = trimmed.split('.').next().unwrap_or_default();
+    if RESERVED_NAMES
+        .iter()
+        .any(|reserved| stem.eq_ignore_ascii_case(reserved))
+    {
+        format!("_{}", trimmed)
+    } else {
+        trimmed.to_string()
+    }
+}
+
+/// 按策略处理一段路径中Windows上不可用的名称
+fn check_component<'a>(
+    name: &str,
+    offset: u64,
+    part: &'a str,
+    policy: ReservedNamePolicy,
+) -> Result<Cow<'a, str>, MpkgError> {
+    if policy == ReservedNamePolicy::Allow || !is_reserved_component(part) {
+        return Ok(Cow::Borrowed(part));
+    }
+    match policy {
+        ReservedNamePolicy::Error => Err(MpkgError::ReservedName {
+            name: name.to_string(),
+            offset,
+        }),
+        _ => Ok(Cow::Owned(rewrite_component(part))),
+    }
+}
+
+/// 判断文件名是否以Windows盘符开头，例如 `C:`
+fn has_drive_prefix(name: &str) -> bool {
+    let bytes = name.as_bytes();
+    bytes.len() >= 2 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':'
+}
+
+/// 将包内文件名规范化为输出目录下的相对路径
+///
+/// 绝对路径、盘符前缀以及通过 `..` 跳出输出目录的文件名都会被拒绝，返回 `MpkgError::UnsafePath`。
+/// 规范化后为空（例如空字符串或 `a/..`）或以目录分隔符结尾的文件名不对应任何文件，
+/// 返回 `MpkgError::BadEntryName`；包内不支持单独的目录项。
+///
+/// `normalize_separators` 为真时先把 `\` 视为目录分隔符，以便在非Windows平台上还原Windows下打包的目录结构。
+/// Windows上不可用的名称按 `reserved_names` 处理。
+pub(crate) fn sanitize_entry_path(
+    name: &str,
+    offset: u64,
+    options: &UnpackOptions,
+) -> Result<PathBuf, MpkgError> {
+    let unsafe_path = || MpkgError::UnsafePath {
+        name: name.to_string(),
+        offset,
+    };
+    let bad_name = || MpkgError::BadEntryName {
+        name: name.to_string(),
+        offset,
+    };
+
+    // 无论当前平台如何，都拒绝Windows风格的绝对路径
+    if has_drive_prefix(name) || name.starts_with('\\') {
+        return Err(unsafe_path());
+    }
+
+    // 替换分隔符后再检查各部分，避免 `a\..\..` 之类的文件名跳出输出目录
+    let name_with_separators = if options.normalize_separators {
+        name.replace('\\', "/")
+    } else {
+        name.to_string()
+    };
+    if name_with_separators.ends_with('/') {
+        return Err(bad_name());
+    }
+
+    let mut normalized = PathBuf::new();
+    for component in Path::new(&name_with_separators).components() {
+        match component {
+            Component::Normal(part) => {
+                let part = part.to_string_lossy();
+                normalized.push(&*check_component(
+                    name,
+                    offset,
+                    &part,
+                    options.reserved_names,
+                )?);
+            }
+            Component::CurDir => {}
+            Component::ParentDir => {
+                if !normalized.pop() {
+                    return Err(unsafe_path());
+                }
+            }
+            Component::RootDir | Component::Prefix(_) => return Err(unsafe_path()),
+        }
+    }
+    if normalized.as_os_str().is_empty() {
+        return Err(bad_name());
+    }
+    Ok(normalized)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{quiet, unpack_bytes, ArchiveBuilder};
+
+    #[test]
+    fn rejects_escaping_names() {
+        let options = UnpackOptions::default();
+        for name in [
+            "../escape.txt",
+            "../../etc/passwd",
+            "a/../../escape.txt",
+            "/etc/passwd",
+            "C:\\Windows\\evil.dll",
+            "c:evil.txt",
+            "\\\\server\\share\\evil.txt",
+        ] {
+            assert!(
+                matches!(
+                    sanitize_entry_path(name, 0, &options),
+                    Err(MpkgError::UnsafePath { .. })
+                ),
+                "{}",
+                name
+            );
+        }
+        assert_eq!(
+            sanitize_entry_path("a/../b/./c.txt", 0, &options).unwrap(),
+            Path::new("b/c.txt")
+        );
+    }
+
+    #[test]
+    fn malicious_table_writes_nothing() {
+        for name in ["../escape.txt", "/tmp/escape.txt", "C:\\escape.txt"] {
+            let dir = tempfile::tempdir().unwrap();
+            let bytes = ArchiveBuilder::new("PKGV0001")
+                .file("ok.txt", b"ok")
+                .file(name, b"evil")
+                .build();
+            let result = unpack_bytes(&bytes, &dir.path().join("nested"), &quiet());
+            // 第二个文件名位于偏移38
+            assert!(
+                matches!(result, Err(MpkgError::UnsafePath { offset: 38, .. })),
+                "{}: {:?}",
+                name,
+                result
+            );
+            // 文件名在写入任何文件之前检查，输出文件夹及其上级都不会出现任何文件
+            assert_eq!(fs_entries(dir.path()), Vec::<PathBuf>::new(), "{}", name);
+        }
+    }
+
+    #[test]
+    fn rejects_empty_and_directory_names() {
+        let options = UnpackOptions::default();
+        for name in ["", ".", "a/..", "assets/", "assets/textures/"] {
+            assert!(
+                matches!(
+                    sanitize_entry_path(name, 0, &options),
+                    Err(MpkgError::BadEntryName { .. })
+                ),
+                "{:?}",
+                name
+            );
+        }
+
+        // 启用 `normalize_separators` 时以 `\` 结尾的文件名同样视为目录
+        let options = UnpackOptions {
+            normalize_separators: true,
+            ..UnpackOptions::default()
+        };
+        assert!(matches!(
+            sanitize_entry_path("assets\\", 0, &options),
+            Err(MpkgError::BadEntryName { .. })
+        ));
+
+        let dir = tempfile::tempdir().unwrap();
+        let bytes = ArchiveBuilder::new("PKGV0001")
+            .file("a.txt", b"a")
+            .file("", b"")
+            .build();
+        let result = unpack_bytes(&bytes, dir.path(), &quiet());
+        assert!(
+            matches!(result, Err(MpkgError::BadEntryName { offset: 37, .. })),
+            "{:?}",
+            result
+        );
+        assert!(!dir.path().join("out").exists());
+    }
+
+    /// 递归列出文件夹中的所有文件
+    fn fs_entries(dir: &Path) -> Vec<PathBuf> {
+        let mut files = Vec::new();
+        for entry in std::fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            if path.is_dir() {
+                files.extend(fs_entries(&path));
+            } else {
+                files.push(path);
+            }
+        }
+        files
+    }
+}
@@ -0,0 +1,128 @@
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use crate::buffered::SeekBufReader;
+use crate::{
+    check_cancelled, output_capacity, prepare_archive, record_skipped, write_entry_data, MpkgError,
+    UnpackOptions, UnpackReport, DEFAULT_MAX_MAP_SIZE,
+};
+
+/// 解包输出的目标，可替换为内存、压缩包或远程存储等实现
+///
+/// 路径均为相对于输出根目录的路径，已经过安全检查，不会包含 `..` 或绝对路径。
+pub trait OutputSink {
+    /// 创建（或截断）一个文件并返回其写入器
+    fn create_file(&self, rel_path: &Path) -> io::Result<Box<dyn Write>>;
+
+    /// 创建文件夹及其所有上级文件夹，`rel_path` 为空时表示输出根目录
+    fn create_dir(&self, rel_path: &Path) -> io::Result<()>;
+}
+
+/// 写入本地文件系统中 `root` 文件夹的 `OutputSink`
+#[derive(Debug, Clone)]
+pub struct FsSink {
+    root: PathBuf,
+}
+
+impl FsSink {
+    /// 以 `root` 为输出根目录
+    pub fn new<P: Into<PathBuf>>(root: P) -> FsSink {
+        FsSink { root: root.into() }
+    }
+}
+
+impl OutputSink for FsSink {
+    fn create_file(&self, rel_path: &Path) -> io::Result<Box<dyn Write>> {
+        Ok(Box::new(File::create(self.root.join(rel_path))?))
+    }
+
+    fn create_dir(&self, rel_path: &Path) -> io::Result<()> {
+        fs::create_dir_all(self.root.join(rel_path))
+    }
+}
+
+/// 从流中解包MPKG文件，所有输出都交给 `sink` 写入
+///
+/// 文件名的筛选、重命名与安全检查，以及 `verify` 与解压都与 `unpack_with_options` 相同；
+/// `overwrite`、`resume`、`refuse_symlinks`、`atomic`、`preserve_timestamps`、`manifest`
+/// 等依赖本地文件系统的选项不会生效。此函数不打印任何进度。
+pub fn unpack_to_sink<R, S>(
+    mut reader: R,
+    sink: &S,
+    options: &UnpackOptions,
+) -> Result<UnpackReport, MpkgError>
+where
+    R: Read + Seek,
+    S: OutputSink + ?Sized,
+{
+    options.validate()?;
+
+    // 以空路径为根计算输出路径，得到相对于输出根目录的路径
+    let archive = prepare_archive(&mut reader, Path::new(""), options)?;
+    sink.create_dir(Path::new(""))?;
+
+    let mut report = UnpackReport {
+        version: archive.version.clone(),
+        ..UnpackReport::default()
+    };
+    for (entry, target) in archive.entries.iter().zip(&archive.targets) {
+        check_cancelled(options.cancel.as_deref())?;
+        let Some(target) = target else {
+            record_skipped(&mut report, entry, options);
+            continue;
+        };
+
+        if let Some(parent_dir) = target.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            sink.create_dir(parent_dir)?;
+        }
+        reader.seek(SeekFrom::Start(entry.offset))?;
+        let mut output_stream =
+            BufWriter::with_capacity(output_capacity(entry, options), sink.create_file(target)?);
+        let written = write_entry_data(&mut reader, &mut output_stream, entry, options, |_| {})?;
+        output_stream.flush()?;
+
+        report.files_extracted += 1;
+        report.bytes_written += written;
+    }
+    Ok(report)
+}
+
+/// 将MPKG文件中的所有文件读入内存，返回文件名到数据的映射，不写入磁盘
+///
+/// 文件表记录的总大小超过 `DEFAULT_MAX_MAP_SIZE` 时返回 `MpkgError::TotalSizeExceeded`，
+/// 需要其他上限时使用 `unpack_to_map_with_options` 并设置 `max_total_size`。
+pub fn unpack_to_map<P: AsRef<Path>>(path: P) -> Result<BTreeMap<String, Vec<u8>>, MpkgError> {
+    let options = UnpackOptions {
+        max_total_size: Some(DEFAULT_MAX_MAP_SIZE),
+        ..UnpackOptions::default()
+    };
+    unpack_to_map_with_options(path, &options)
+}
+
+/// 按给定选项将MPKG文件读入内存，返回文件名到数据的映射
+///
+/// 键为文件表中的原始文件名，不受 `rename`、`flatten` 等选项影响，同名文件只保留最后一个；
+/// 筛选、`verify` 与解压与 `unpack_to_sink` 相同。`max_total_size` 为 `None` 时不限制总大小。
+pub fn unpack_to_map_with_options<P: AsRef<Path>>(
+    path: P,
+    options: &UnpackOptions,
+) -> Result<BTreeMap<String, Vec<u8>>, MpkgError> {
+    options.validate()?;
+    let mut reader = SeekBufReader::new(File::open(path)?);
+    let archive = prepare_archive(&mut reader, Path::new(""), options)?;
+
+    let mut files = BTreeMap::new();
+    for (entry, target) in archive.entries.iter().zip(&archive.targets) {
+        check_cancelled(options.cancel.as_deref())?;
+        if target.is_none() {
+            continue;
+        }
+        reader.seek(SeekFrom::Start(entry.offset))?;
+        let mut data = Vec::with_capacity(usize::try_from(entry.size).unwrap_or(0));
+        write_entry_data(&mut reader, &mut data, entry, options, |_| {})?;
+        files.insert(entry.name.clone(), data);
+    }
+    Ok(files)
+}
@@ -0,0 +1,99 @@
+use std::ffi::OsString;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// 与输出文件夹同级的临时文件夹，例如 `out/a` -> `out/a.partial`
+pub(crate) fn partial_folder(unpacked_folder: &Path) -> PathBuf {
+    let mut name = OsString::from(unpacked_folder.file_name().unwrap_or_default());
+    name.push(".partial");
+    unpacked_folder.with_file_name(name)
+}
+
+/// 替换时暂存原有输出文件夹的同级文件夹，例如 `out/a` -> `out/a.old`
+fn old_folder(unpacked_folder: &Path) -> PathBuf {
+    let mut name = OsString::from(unpacked_folder.file_name().unwrap_or_default());
+    name.push(".old");
+    unpacked_folder.with_file_name(name)
+}
+
+/// 递归复制文件夹
+fn copy_dir(from: &Path, to: &Path) -> io::Result<()> {
+    fs::create_dir_all(to)?;
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let target = to.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_dir(&entry.path(), &target)?;
+        } else {
+            fs::copy(entry.path(), &target)?;
+        }
+    }
+    Ok(())
+}
+
+/// 将临时文件夹移动到最终位置，已存在的同名文件夹会被替换
+///
+/// 原有的文件夹先被重命名为同级的 `.old` 文件夹，移动成功后才删除；移动失败时恢复原有的文件夹，
+/// 临时文件夹保持不变。优先直接重命名；两者不在同一文件系统上时改为复制后删除。
+pub(crate) fn commit_folder(partial: &Path, unpacked_folder: &Path) -> io::Result<()> {
+    let old = old_folder(unpacked_folder);
+    // 清理上次替换时没能删除的旧文件夹
+    if old.exists() {
+        fs::remove_dir_all(&old)?;
+    }
+    let replacing = unpacked_folder.exists();
+    if replacing {
+        fs::rename(unpacked_folder, &old)?;
+    }
+
+    let moved = match fs::rename(partial, unpacked_folder) {
+        Err(e) if e.kind() == io::ErrorKind::CrossesDevices => copy_dir(partial, unpacked_folder)
+            .inspect_err(|_| {
+                let _ = fs::remove_dir_all(unpacked_folder);
+            }),
+        result => result,
+    };
+    if let Err(e) = moved {
+        if replacing {
+            fs::rename(&old, unpacked_folder)?;
+        }
+        return Err(e);
+    }
+
+    // 已经提交成功，旧文件夹或复制后剩下的临时文件夹删除失败只会留下多余的文件
+    if replacing {
+        let _ = fs::remove_dir_all(&old);
+    }
+    if partial.exists() {
+        let _ = fs::remove_dir_all(partial);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn commit_replaces_or_restores() {
+        let dir = tempfile::tempdir().unwrap();
+        let unpacked_folder = dir.path().join("a");
+        let partial = partial_folder(&unpacked_folder);
+        fs::create_dir_all(&unpacked_folder).unwrap();
+        fs::write(unpacked_folder.join("old.txt"), b"old").unwrap();
+
+        // 移动失败时原有的文件夹保持不变
+        assert!(commit_folder(&partial, &unpacked_folder).is_err());
+        assert_eq!(fs::read(unpacked_folder.join("old.txt")).unwrap(), b"old");
+        assert!(!old_folder(&unpacked_folder).exists());
+
+        fs::create_dir_all(&partial).unwrap();
+        fs::write(partial.join("new.txt"), b"new").unwrap();
+        commit_folder(&partial, &unpacked_folder).unwrap();
+        assert_eq!(fs::read(unpacked_folder.join("new.txt")).unwrap(), b"new");
+        assert!(!unpacked_folder.join("old.txt").exists());
+        assert!(!partial.exists());
+        assert!(!old_folder(&unpacked_folder).exists());
+    }
+}
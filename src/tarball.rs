@@ -0,0 +1,25 @@
+use std::io::Write;
+use std::path::Path;
+
+use crate::sanitize::sanitize_entry_path;
+use crate::{MpkgArchive, MpkgError, UnpackOptions};
+
+/// 将MPKG文件中的所有文件依次写入 `out` 的tar流，不在磁盘上生成单独的文件
+///
+/// 包内文件名经过安全检查后作为tar中的路径，文件大小已知，因此无需缓冲整个文件。
+pub fn repack_to_tar<P: AsRef<Path>, W: Write>(archive: P, out: W) -> Result<(), MpkgError> {
+    let archive = MpkgArchive::open(archive)?;
+    let mut builder = tar::Builder::new(out);
+
+    for (entry, data) in archive.entries() {
+        let path = sanitize_entry_path(&entry.name, entry.name_offset, &UnpackOptions::default())?;
+        let mut header = tar::Header::new_gnu();
+        header.set_size(entry.size);
+        header.set_mode(0o644);
+        header.set_mtime(entry.modified.unwrap_or(0));
+        builder.append_data(&mut header, path, data)?;
+    }
+
+    builder.finish()?;
+    Ok(())
+}
@@ -0,0 +1,196 @@
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet};
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+
+use crate::glob::glob_match;
+use crate::sanitize::sanitize_entry_path;
+use crate::{DuplicatePolicy, MpkgEntry, MpkgError, SizeOrder, UnpackOptions};
+
+/// 判断文件名是否匹配 `exclude` 中的任一通配符
+pub(crate) fn is_excluded(entry: &MpkgEntry, options: &UnpackOptions) -> bool {
+    options
+        .exclude
+        .iter()
+        .any(|pattern| glob_match(pattern, &entry.name))
+}
+
+/// 根据选项判断是否需要解包该文件，`exclude` 优先于 `filter`
+fn is_selected(entry: &MpkgEntry, options: &UnpackOptions) -> bool {
+    if (options.skip_empty && entry.size == 0) || is_excluded(entry, options) {
+        return false;
+    }
+    options
+        .filter
+        .as_deref()
+        .is_none_or(|pattern| glob_match(pattern, &entry.name))
+}
+
+/// 计算将要解包的文件按文件表记录的总大小，超过 `max_total_size` 时返回错误
+pub(crate) fn check_total_size(
+    entries: &[MpkgEntry],
+    targets: &[Option<PathBuf>],
+    options: &UnpackOptions,
+) -> Result<(), MpkgError> {
+    let Some(limit) = options.max_total_size else {
+        return Ok(());
+    };
+    let total = entries
+        .iter()
+        .zip(targets)
+        .filter(|(_, target)| target.is_some())
+        .fold(0u64, |total, (entry, _)| total.saturating_add(entry.size));
+    if total > limit {
+        return Err(MpkgError::TotalSizeExceeded { total, limit });
+    }
+    Ok(())
+}
+
+/// 在文件名主干后追加 `_n`，保留扩展名，例如 `a.png` -> `a_1.png`
+fn numbered_name(name: &Path, n: usize) -> PathBuf {
+    let mut numbered = OsString::from(name.file_stem().unwrap_or_default());
+    numbered.push(format!("_{}", n));
+    if let Some(extension) = name.extension() {
+        numbered.push(".");
+        numbered.push(extension);
+    }
+    PathBuf::from(numbered)
+}
+
+/// 只保留文件名的最后一部分；与已使用的名称重复时依次尝试追加 `_1`、`_2` 等后缀
+fn flatten_path(path: PathBuf, used: &mut HashSet<PathBuf>) -> PathBuf {
+    let name = path.file_name().map(PathBuf::from).unwrap_or(path);
+    let mut candidate = name.clone();
+    let mut n = 0;
+    while used.contains(&candidate) {
+        n += 1;
+        candidate = numbered_name(&name, n);
+    }
+    used.insert(candidate.clone());
+    candidate
+}
+
+/// 去掉路径开头的 `prefix`，不以其开头或去掉后为空时原样返回
+fn strip_prefix(path: PathBuf, prefix: &str) -> PathBuf {
+    match path.strip_prefix(prefix) {
+        Ok(stripped) if !stripped.as_os_str().is_empty() => stripped.to_path_buf(),
+        _ => path,
+    }
+}
+
+/// 按 `policy` 处理写到同一输出路径的文件，被舍弃的文件改为 `None`
+fn resolve_duplicates(
+    entries: &[MpkgEntry],
+    targets: &mut [Option<PathBuf>],
+    policy: DuplicatePolicy,
+) -> Result<(), MpkgError> {
+    // 输出路径到当前占用它的文件序号
+    let mut owners: HashMap<PathBuf, usize> = HashMap::new();
+    for i in 0..targets.len() {
+        let Some(target) = targets[i].clone() else {
+            continue;
+        };
+        let Some(&owner) = owners.get(&target) else {
+            owners.insert(target, i);
+            continue;
+        };
+        match policy {
+            DuplicatePolicy::LastWins => {
+                targets[owner] = None;
+                owners.insert(target, i);
+            }
+            DuplicatePolicy::FirstWins => targets[i] = None,
+            DuplicatePolicy::Error => {
+                return Err(MpkgError::DuplicateEntry {
+                    name: entries[i].name.clone(),
+                    first_offset: entries[owner].offset,
+                    second_offset: entries[i].offset,
+                    first_archive: None,
+                    second_archive: None,
+                });
+            }
+            DuplicatePolicy::Rename => {
+                let mut n = 1;
+                let mut candidate = target.with_file_name(numbered_name(&target, n));
+                while owners.contains_key(&candidate) {
+                    n += 1;
+                    candidate = target.with_file_name(numbered_name(&target, n));
+                }
+                owners.insert(candidate.clone(), i);
+                targets[i] = Some(candidate);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// 只保留按 `order` 排列的前 `n` 个仍有输出路径的文件，其余改为 `None`
+fn keep_top_n(entries: &[MpkgEntry], targets: &mut [Option<PathBuf>], n: usize, order: SizeOrder) {
+    let mut selected: Vec<usize> = (0..targets.len())
+        .filter(|&i| targets[i].is_some())
+        .collect();
+    // 稳定排序，大小相同时保持文件表中的顺序
+    match order {
+        SizeOrder::Largest => selected.sort_by_key(|&i| Reverse(entries[i].size)),
+        SizeOrder::Smallest => selected.sort_by_key(|&i| entries[i].size),
+    }
+    for &i in selected.iter().skip(n) {
+        targets[i] = None;
+    }
+}
+
+/// 计算每个文件的输出路径，`None` 表示跳过该文件
+///
+/// 设置了 `rename` 时先按其结果重命名或跳过文件，允许的绝对路径原样使用；
+/// 所有文件名都会经过安全检查，确保不会跳出 `unpacked_folder`，之后去掉 `strip_prefix`，
+/// 启用 `lowercase_names` 时转换为小写；
+/// 启用 `flatten` 时再丢弃目录结构，重名文件按出现顺序追加 `_1`、`_2` 等后缀；
+/// 之后按 `duplicates` 处理仍会写到同一路径的文件，最后按 `extract_top_n` 挑选文件。
+pub(crate) fn plan_targets(
+    entries: &[MpkgEntry],
+    unpacked_folder: &Path,
+    options: &UnpackOptions,
+) -> Result<Vec<Option<PathBuf>>, MpkgError> {
+    let mut used = HashSet::new();
+    let mut targets = entries
+        .iter()
+        .map(|entry| {
+            if !is_selected(entry, options) {
+                return Ok(None);
+            }
+            // 安全检查作用于重命名之后的文件名
+            let name = match &options.rename {
+                Some(rename) => match rename.call(entry) {
+                    Some(path) if path.is_absolute() && options.allow_absolute_routes => {
+                        return Ok(Some(path));
+                    }
+                    Some(path) => path
+                        .to_str()
+                        .ok_or_else(|| MpkgError::UnsafePath {
+                            name: path.display().to_string(),
+                            offset: entry.name_offset,
+                        })?
+                        .to_string(),
+                    None => return Ok(None),
+                },
+                None => entry.name.clone(),
+            };
+            let mut relative = sanitize_entry_path(&name, entry.name_offset, options)?;
+            if let Some(prefix) = options.strip_prefix.as_deref() {
+                relative = strip_prefix(relative, prefix);
+            }
+            if options.lowercase_names {
+                relative = PathBuf::from(relative.to_string_lossy().to_lowercase());
+            }
+            if options.flatten {
+                relative = flatten_path(relative, &mut used);
+            }
+            Ok(Some(unpacked_folder.join(relative)))
+        })
+        .collect::<Result<Vec<_>, MpkgError>>()?;
+    resolve_duplicates(entries, &mut targets, options.duplicates)?;
+    if let Some(n) = options.extract_top_n {
+        keep_top_n(entries, &mut targets, n, options.size_order);
+    }
+    Ok(targets)
+}
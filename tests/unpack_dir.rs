@@ -0,0 +1,54 @@
+use std::fs;
+
+use mpkg_unpack::{unpack_dir_with_options, LogLevel, MpkgError, UnpackOptions};
+
+/// 只有一个文件 `hello.txt` 的 `PKGV0001` 包
+fn one_file_archive() -> Vec<u8> {
+    let version = b"PKGV0001";
+    let name = b"hello.txt";
+    let data = b"hello";
+    let mut bytes = Vec::new();
+    bytes.extend_from_slice(&(version.len() as u32).to_le_bytes());
+    bytes.extend_from_slice(version);
+    bytes.extend_from_slice(&1u32.to_le_bytes());
+    bytes.extend_from_slice(&(name.len() as u32).to_le_bytes());
+    bytes.extend_from_slice(name);
+    bytes.extend_from_slice(&0u32.to_le_bytes());
+    bytes.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    bytes.extend_from_slice(data);
+    bytes
+}
+
+#[test]
+fn failures_do_not_stop_the_scan() {
+    let input = tempfile::tempdir().unwrap();
+    let output = tempfile::tempdir().unwrap();
+    let archive = one_file_archive();
+    fs::write(input.path().join("a.mpkg"), &archive).unwrap();
+    // 数据被截断的包
+    fs::write(input.path().join("b.mpkg"), &archive[..archive.len() - 2]).unwrap();
+    fs::write(input.path().join("c.txt"), b"not an archive").unwrap();
+
+    let options = UnpackOptions {
+        log_level: LogLevel::Quiet,
+        ..UnpackOptions::default()
+    };
+    let results = unpack_dir_with_options(input.path(), output.path(), &options).unwrap();
+
+    // 扩展名不符的文件被跳过，不出现在结果中
+    let names: Vec<_> = results
+        .iter()
+        .map(|(path, _)| path.file_name().unwrap().to_str().unwrap())
+        .collect();
+    assert_eq!(names, ["a.mpkg", "b.mpkg"]);
+    assert!(results[0].1.is_ok(), "{:?}", results[0].1);
+    assert!(
+        matches!(results[1].1, Err(MpkgError::DataTruncated { .. })),
+        "{:?}",
+        results[1].1
+    );
+
+    let extracted = fs::read(output.path().join("a").join("hello.txt")).unwrap();
+    assert_eq!(extracted, b"hello");
+    assert!(!output.path().join("c").exists());
+}